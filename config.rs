@@ -0,0 +1,50 @@
+//! Settings that stick between runs, kept next to the saves in `saves/config.json`:
+//!
+//! ```json
+//! { "tileset": "unicode" }
+//! ```
+//!
+//! Anything missing from the file gets its default, so older files keep working as settings
+//! are added.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::save::{FileError, SAVE_DIR};
+use crate::tiles::Tileset;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub tileset: Tileset,
+}
+
+fn config_path() -> PathBuf {
+    PathBuf::from(SAVE_DIR).join("config.json")
+}
+
+/// the saved settings; no file yet just means the defaults
+pub fn load() -> Result<Config, FileError> {
+    let json = match fs::read_to_string(config_path()) {
+        Ok(json) => json,
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub fn save(config: &Config) -> Result<(), FileError> {
+    fs::create_dir_all(SAVE_DIR)?;
+    fs::write(config_path(), serde_json::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+#[test]
+fn config_tests() {
+    // settings the file doesn't mention keep their defaults
+    let empty: Config = serde_json::from_str("{}").unwrap();
+    assert_eq!(empty, Config::default());
+    let cp437: Config = serde_json::from_str(r#"{ "tileset": "cp437" }"#).unwrap();
+    assert_eq!(cp437.tileset, Tileset::Cp437);
+    assert!(serde_json::from_str::<Config>(r#"{ "tileset": "ebcdic" }"#).is_err());
+}
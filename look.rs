@@ -0,0 +1,294 @@
+//! Looking around: what's in view and where, and a closer description of any one thing.
+//! Everything here works in map coordinates, whatever part of the map is on screen.
+
+use std::collections::HashSet;
+
+use crate::{health_state, Ai, Door, Entity, Equipment, Game, Item, Slot, Terrain, MAP_HEIGHT, MAP_WIDTH, PLAYER};
+
+/// something the player can see
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Entity(usize),
+    Tile(i32, i32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sighting {
+    pub target: Target,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// "2 north 1 east", or "here"
+pub fn direction(dx: i32, dy: i32) -> String {
+    let mut parts = Vec::new();
+    if dy != 0 {
+        parts.push(format!("{} {}", dy.abs(), if dy < 0 { "north" } else { "south" }));
+    }
+    if dx != 0 {
+        parts.push(format!("{} {}", dx.abs(), if dx < 0 { "west" } else { "east" }));
+    }
+    if parts.is_empty() {
+        "here".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// we only move north, south, east and west, so this is how many moves away something is
+pub fn steps(dx: i32, dy: i32) -> i32 {
+    dx.abs() + dy.abs()
+}
+
+/// terrain worth pointing out; floor and walls go without saying
+pub fn notable(terrain: Terrain) -> bool {
+    !matches!(terrain, Terrain::Floor | Terrain::Wall)
+}
+
+pub fn terrain_name(terrain: Terrain) -> &'static str {
+    match terrain {
+        Terrain::Floor => "floor",
+        Terrain::Wall => "wall",
+        Terrain::Water => "shallow water",
+        Terrain::DeepWater => "deep water",
+        Terrain::Lava => "lava",
+        Terrain::Rubble => "rubble",
+        Terrain::Grass => "grass",
+        Terrain::TallGrass => "tall grass",
+        Terrain::Chasm => "chasm",
+        Terrain::Door(Door::Open) => "open door",
+        Terrain::Door(_) => "door",
+        Terrain::UpStairs => "stairs up",
+        Terrain::DownStairs => "stairs down",
+    }
+}
+
+fn terrain_description(terrain: Terrain) -> &'static str {
+    match terrain {
+        Terrain::Floor => "Bare stone floor.",
+        Terrain::Wall => "Solid rock.",
+        Terrain::Water => "Knee-deep water. Wading through it is slow.",
+        Terrain::DeepWater => "Dark, deep water. Only swimmers can cross it.",
+        Terrain::Lava => "Molten rock. Stepping in it burns.",
+        Terrain::Rubble => "Loose stones and broken masonry. Slow going.",
+        Terrain::Grass => "Short, trampled grass.",
+        Terrain::TallGrass => "Grass tall enough to hide behind. Walking through flattens it.",
+        Terrain::Chasm => "A deep crack in the floor. Falling in takes you down a level, painfully.",
+        Terrain::Door(Door::Open) => "An open wooden door.",
+        Terrain::Door(Door::Closed) => "A closed wooden door.",
+        Terrain::Door(Door::Locked) => "A closed wooden door with a sturdy lock.",
+        Terrain::UpStairs => "A staircase leading up.",
+        Terrain::DownStairs => "A staircase leading further down.",
+    }
+}
+
+/// everything in view, nearest first: the entities, then the nearest of each notable kind of terrain
+pub fn in_view(entities: &[Entity], game: &Game, seen: &HashSet<(i32, i32)>) -> Vec<Sighting> {
+    let (px, py) = entities[PLAYER].pos();
+    let distance = |s: &Sighting| steps(s.x - px, s.y - py);
+
+    let mut things: Vec<Sighting> = entities
+        .iter()
+        .enumerate()
+        .filter(|&(id, e)| id != PLAYER && seen.contains(&e.pos()))
+        .map(|(id, e)| {
+            let name = if e.item.is_some() { e.stack_name(game) } else { e.name.clone() };
+            Sighting { target: Target::Entity(id), name, x: e.x, y: e.y }
+        })
+        .collect();
+    things.sort_by_key(distance);
+
+    let mut features: Vec<Sighting> = Vec::new();
+    let mut tiles: Vec<&(i32, i32)> = seen.iter().filter(|&&(x, y)| (0..MAP_WIDTH).contains(&x) && (0..MAP_HEIGHT).contains(&y)).collect();
+    tiles.sort_by_key(|&&(x, y)| (steps(x - px, y - py), x, y));
+    for &(x, y) in tiles {
+        let tile = &game.map[x as usize][y as usize];
+        let name = match tile.trap {
+            Some(trap) if !trap.hidden => trap.kind.to_string(),
+            _ if notable(tile.terrain) => terrain_name(tile.terrain).to_string(),
+            _ => continue,
+        };
+        if !features.iter().any(|f| f.name == name) {
+            features.push(Sighting { target: Target::Tile(x, y), name, x, y });
+        }
+    }
+
+    things.extend(features);
+    things
+}
+
+/// one line per sighting, lettered so they can be picked
+pub fn list(entities: &[Entity], sightings: &[Sighting]) -> Vec<String> {
+    let (px, py) = entities[PLAYER].pos();
+    sightings
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let (dx, dy) = (s.x - px, s.y - py);
+            let letter = (b'a' + (i % 26) as u8) as char;
+            format!("({}) {}: {} ({} steps)", letter, s.name, direction(dx, dy), steps(dx, dy))
+        })
+        .collect()
+}
+
+/// by letter from the list, or by (part of) its name
+pub fn find<'a>(sightings: &'a [Sighting], query: &str) -> Option<&'a Sighting> {
+    let query = query.trim().to_lowercase();
+    let mut chars = query.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_lowercase() {
+            return sightings.get(c as usize - 'a' as usize);
+        }
+    }
+    sightings.iter().find(|s| !query.is_empty() && s.name.to_lowercase().contains(&query))
+}
+
+/// a closer look at one thing
+pub fn describe(target: &Target, entities: &[Entity], game: &Game) -> Vec<String> {
+    match *target {
+        Target::Tile(x, y) => describe_tile(x, y, game),
+        Target::Entity(id) if entities[id].fighter.is_some() => describe_monster(&entities[id], game),
+        Target::Entity(id) if entities[id].item.is_some() => describe_item(&entities[id], &entities[PLAYER], game),
+        Target::Entity(id) => vec![entities[id].name.clone(), "Nothing left to worry about.".to_string()],
+    }
+}
+
+fn describe_tile(x: i32, y: i32, game: &Game) -> Vec<String> {
+    let tile = &game.map[x as usize][y as usize];
+    let mut lines = vec![terrain_name(tile.terrain).to_string(), terrain_description(tile.terrain).to_string()];
+    if let Some(trap) = tile.trap {
+        if !trap.hidden {
+            lines.push(format!("There is a {} here. You could try to disarm it.", trap.kind));
+        }
+    }
+    lines
+}
+
+fn describe_monster(monster: &Entity, game: &Game) -> Vec<String> {
+    let mut lines = vec![monster.name.clone()];
+    if let Some(ref description) = monster.description {
+        lines.push(description.clone());
+    }
+    let fighter = monster.fighter.unwrap();
+    lines.push(format!("It looks {}.", health_state(fighter.hp, monster.get_max_hp())));
+    let gear: Vec<String> = monster
+        .inventory
+        .iter()
+        .filter(|item| item.equipment.is_some_and(|e| e.equipped))
+        .map(|item| game.item_name(item))
+        .collect();
+    if !gear.is_empty() {
+        lines.push(format!("It has {}.", gear.join(" and ")));
+    }
+    // you can only look at what's in view, and anything in view comes after you
+    lines.push(match monster.ai {
+        Some(Ai::Hunting) => "It is hunting you, and won't lose track of you out of sight.".to_string(),
+        _ => "It is coming for you.".to_string(),
+    });
+    if monster.slowed > 0 {
+        lines.push("It is slowed down.".to_string());
+    }
+    if monster.poisoned > 0 {
+        lines.push("It looks sick.".to_string());
+    }
+    lines
+}
+
+fn describe_item(item: &Entity, player: &Entity, game: &Game) -> Vec<String> {
+    let mut lines = vec![item.stack_name(game)];
+    if !game.is_identified(&item.name) {
+        lines.push("You don't know what it does yet.".to_string());
+        return lines;
+    }
+    if let Some(ref description) = item.description {
+        lines.push(description.clone());
+    }
+    match item.item {
+        Some(Item::Food(nutrition)) => lines.push(format!("It would feed you for about {} turns.", nutrition)),
+        Some(Item::Equipment) => {
+            let equipment = item.equipment.unwrap();
+            let hands = if equipment.two_handed { " (both hands)" } else { "" };
+            lines.push(format!("Worn on: {}{}", equipment.slot, hands));
+            lines.push(format!(
+                "Damage {:+}, defence {:+}, max HP {:+}",
+                equipment.damage_bonus, equipment.defense_bonus, equipment.max_hp_bonus
+            ));
+            lines.push(compare(item, player, game));
+        }
+        _ => {}
+    }
+    lines
+}
+
+/// how it measures up against what the player would have to take off to wear it
+fn compare(item: &Entity, player: &Entity, game: &Game) -> String {
+    let equipment = item.equipment.unwrap();
+    if equipment.equipped {
+        return "You are wearing it.".to_string();
+    }
+    let worn_in = |slots: &[Slot]| -> Vec<&Entity> {
+        player
+            .inventory
+            .iter()
+            .filter(|i| i.equipment.is_some_and(|e| e.equipped && slots.iter().any(|&slot| e.occupies(slot))))
+            .collect()
+    };
+    let mut replaced = worn_in(&equipment.slots_needed());
+    // a ring goes on the other finger if that one's free
+    if let Some(other) = equipment.slot.alternative() {
+        if worn_in(&[other]).is_empty() {
+            replaced.clear();
+        }
+    }
+    if replaced.is_empty() {
+        return "You have nothing in the way of wearing it.".to_string();
+    }
+    let total = |bonus: fn(&Equipment) -> i32| -> i32 { replaced.iter().map(|i| bonus(&i.equipment.unwrap())).sum() };
+    let names: Vec<String> = replaced.iter().map(|i| game.item_name(i)).collect();
+    format!(
+        "Compared with your {}: damage {:+}, defence {:+}, max HP {:+}",
+        names.join(" and "),
+        equipment.damage_bonus - total(|e| e.damage_bonus),
+        equipment.defense_bonus - total(|e| e.defense_bonus),
+        equipment.max_hp_bonus - total(|e| e.max_hp_bonus)
+    )
+}
+
+#[test]
+fn look_tests() {
+    use crate::{fill_map, make_kobold, Tile};
+
+    assert_eq!(direction(2, -3), "3 north 2 east");
+    assert_eq!(direction(0, 0), "here");
+
+    let mut game = Game::new();
+    fill_map(&mut game.map, Tile::empty());
+    game.map[5][3] = Tile::new(Terrain::DownStairs);
+    let mut player = Entity::new(3, 3, '@', "Player");
+    let mut sword = Entity::new(0, 0, '/', "sword");
+    sword.item = Some(Item::Equipment);
+    sword.equipment = Some(Equipment { equipped: true, damage_bonus: 1, ..Equipment::new(Slot::RightHand) });
+    player.inventory.push(sword);
+    let mut axe = Entity::new(3, 4, '/', "axe");
+    axe.item = Some(Item::Equipment);
+    axe.equipment = Some(Equipment { damage_bonus: 3, ..Equipment::new(Slot::RightHand) });
+    let mut kobold = make_kobold(3, 1, 3);
+    kobold.ai = Some(Ai::Hunting);
+    let far_kobold = make_kobold(15, 15, 3);
+    let entities = vec![player, axe, kobold, far_kobold];
+    let seen: HashSet<(i32, i32)> = (0..8).flat_map(|x| (0..8).map(move |y| (x, y))).collect();
+
+    let sightings = in_view(&entities, &game, &seen);
+    let names: Vec<&str> = sightings.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["axe", "kobold", "stairs down"]);
+    assert_eq!(list(&entities, &sightings)[1], "(b) kobold: 2 north (2 steps)");
+
+    assert_eq!(find(&sightings, "c").unwrap().target, Target::Tile(5, 3));
+    let kobold = find(&sightings, "KOBOLD").unwrap();
+    assert!(describe(&kobold.target, &entities, &game).iter().any(|line| line.starts_with("It is hunting you")));
+    let far_kobold = Target::Entity(3);
+    assert!(describe(&far_kobold, &entities, &game).contains(&"It is coming for you.".to_string()));
+    let axe = describe(&find(&sightings, "axe").unwrap().target, &entities, &game);
+    assert_eq!(axe.last().unwrap(), "Compared with your sword: damage +2, defence +0, max HP +0");
+}
@@ -1,63 +1,213 @@
-// external crate
-use rand::Rng;
-
 #[macro_use]
 extern crate serde_derive;
 
 // std
 use std::collections::hash_set::*;
+use std::collections::{BTreeMap, HashMap};
 use std::cmp; //for splitting
 
-//for save/load
-use std::io::{Read, Write};
-use std::fs::File;
-use std::error::Error;
 
+#[macro_use]
+mod messages;
+mod config;
+mod look;
+mod morgue;
+mod narrate;
+mod overview;
 mod ppfov;
-
-// size of the map
-const MAP_WIDTH: i32 = 20;
-const MAP_HEIGHT: i32 = 20;
+mod render;
+use render::Color;
+mod rng;
+mod save;
+mod scores;
+mod term;
+mod tiles;
+
+// size of the map, bigger than the default view so the camera has somewhere to go
+const MAP_WIDTH: i32 = 60;
+const MAP_HEIGHT: i32 = 30;
+
+// rooms are walled off from the rest of the level, with a door or two
+const ROOM_MIN_SIZE: i32 = 5;
+const ROOM_MAX_SIZE: i32 = 7;
+const MAX_ROOM_TRIES: i32 = 30;
+
+// the player is always the first entity
+const PLAYER: usize = 0;
+
+// how often the game saves itself, in player turns (it also saves on every level change)
+const AUTOSAVE_TURNS: u32 = 50;
+
+// what unidentified potions and scrolls can look like
+const POTION_NAMES: &[&str] = &["healing potion", "potion of poison"];
+const POTION_LOOKS: &[&str] = &["murky", "bubbling", "fizzy", "milky", "smoky", "glowing", "oily", "sparkling"];
+const SCROLL_NAMES: &[&str] = &["scroll of identify", "scroll of magic mapping"];
+const SCROLL_SYLLABLES: &[&str] = &["xy", "zzy", "ka", "lu", "ne", "mor", "tha", "zed", "foo", "plu", "ash", "vex"];
 
 type Map = Vec<Vec<Tile>>;
 
 /// A tile of the map and its properties
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 struct Tile {
-    blocked: bool,
-    block_sight: bool,
-    stairs: bool,
+    terrain: Terrain,
+    trap: Option<Trap>,
+    explored: bool, //seen at some point, so we remember what's there
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Trap {
+    kind: TrapKind,
+    hidden: bool, //until found by searching or stepping on it
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum TrapKind {
+    Dart,
+    Pit,
+    Teleport,
+    Alarm,
+    PoisonGas,
+}
+
+impl std::fmt::Display for TrapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TrapKind::Dart => write!(f, "dart trap"),
+            TrapKind::Pit => write!(f, "pit"),
+            TrapKind::Teleport => write!(f, "teleport trap"),
+            TrapKind::Alarm => write!(f, "alarm trap"),
+            TrapKind::PoisonGas => write!(f, "poison gas trap"),
+        }
+    }
+}
+
+/// What a tile is made of, which decides how you can move through it and see past it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum Terrain {
+    Floor,
+    Wall,
+    Water, //slows you down
+    DeepWater, //only swimmers can get in
+    Lava, //burns
+    Rubble, //slows you down
+    Grass,
+    TallGrass, //blocks sight until trampled
+    Chasm, //drops you to the next level
+    Door(Door),
+    UpStairs,
+    DownStairs,
 }
 
 impl Tile {
+    pub fn new(terrain: Terrain) -> Self {
+        Tile { terrain, trap: None, explored: false }
+    }
+
+    //shorthands
     pub fn empty() -> Self {
-        Tile {
-            blocked: false,
-            block_sight: false,
-            stairs: false,
-        }
+        Tile::new(Terrain::Floor)
     }
 
     pub fn wall() -> Self {
-        Tile {
-            blocked: true,
-            block_sight: true,
-            stairs: false,
+        Tile::new(Terrain::Wall)
+    }
+
+    pub fn door(state: Door) -> Self {
+        Tile::new(Terrain::Door(state))
+    }
+
+    /// walls and closed doors; deep water, lava and chasms are up to whoever is moving
+    pub fn blocked(&self) -> bool {
+        match self.terrain {
+            Terrain::Wall => true,
+            Terrain::Door(state) => state != Door::Open,
+            _ => false,
+        }
+    }
+
+    /// closed and locked doors block sight as well as movement
+    pub fn block_sight(&self) -> bool {
+        match self.terrain {
+            Terrain::Wall | Terrain::TallGrass => true,
+            Terrain::Door(state) => state != Door::Open,
+            _ => false,
+        }
+    }
+
+    pub fn door_state(&self) -> Option<Door> {
+        match self.terrain {
+            Terrain::Door(state) => Some(state),
+            _ => None,
+        }
+    }
+
+    /// in the current tileset (see tiles.rs)
+    pub fn glyph(&self) -> char {
+        if self.trap.is_some_and(|t| !t.hidden) {
+            return tiles::tileset().trap();
+        }
+        tiles::tileset().terrain(self.terrain)
+    }
+
+    /// foreground and background for the colour renderer
+    pub fn colors(&self) -> (Color, Option<Color>) {
+        if self.trap.is_some_and(|t| !t.hidden) {
+            return (Color::BrightMagenta, None);
+        }
+        match self.terrain {
+            Terrain::Floor => (Color::White, None),
+            Terrain::Wall => (Color::BrightWhite, None),
+            Terrain::Water => (Color::BrightBlue, None),
+            Terrain::DeepWater => (Color::BrightWhite, Some(Color::Blue)),
+            Terrain::Lava => (Color::BrightYellow, Some(Color::Red)),
+            Terrain::Rubble => (Color::Yellow, None),
+            Terrain::Grass => (Color::Green, None),
+            Terrain::TallGrass => (Color::BrightGreen, None),
+            Terrain::Chasm => (Color::BrightBlack, None),
+            Terrain::Door(_) => (Color::Yellow, None),
+            Terrain::UpStairs | Terrain::DownStairs => (Color::BrightYellow, None),
         }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum Door {
+    Open,
+    Closed,
+    Locked, //needs a key
+}
+
+/// A rectangle on the map, used to characterise a room (walls included).
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+}
 
-    pub fn stairs() -> Self {
-        Tile {
-            blocked: false,
-            block_sight: false,
-            stairs: true,
+impl Rect {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Rect {
+            x1: x,
+            y1: y,
+            x2: x + w - 1,
+            y2: y + h - 1,
         }
     }
+
+    /// returns true if this rectangle overlaps (or touches) another one
+    pub fn intersects_with(&self, other: &Rect) -> bool {
+        (self.x1 <= other.x2 + 1)
+            && (self.x2 + 1 >= other.x1)
+            && (self.y1 <= other.y2 + 1)
+            && (self.y2 + 1 >= other.y1)
+    }
 }
 
 
 // This is a generic entity: the player, a monster, an item, the stairs...
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct Entity {
     x: i32,
     y: i32,
@@ -69,12 +219,21 @@ struct Entity {
     ai: Option<Ai>,
     item: Option<Item>,
     equipment: Option<Equipment>,
+    inventory: Vec<Entity>, //anything with hands can carry stuff
+    quantity: u32, //how many identical items are in this stack
+    hunger: Option<Hunger>,
+    swims: bool,
+    slowed: u32, //turns lost to slow terrain
+    poisoned: u32, //turns of poison left
+    killed_by: Option<String>, //what dealt the killing blow, once dead
+    description: Option<String>, //what the look command says about it
 }
 
 impl Entity {
     pub fn new(x: i32, y: i32, char: char, name: &str) -> Self {
         Entity { x, y, char, name: name.into(), blocks: true, alive: true, fighter: None,
-            ai: None, item: None, equipment: None }
+            ai: None, item: None, equipment: None, inventory: vec![], quantity: 1, hunger: None,
+            swims: false, slowed: 0, poisoned: 0, killed_by: None, description: None }
     }
 
     //shorthand for ease of use
@@ -86,6 +245,23 @@ impl Entity {
         self.y = y;
     }
 
+    /// can the two be merged into a single stack?
+    pub fn stacks_with(&self, other: &Entity) -> bool {
+        match (self.item, other.item) {
+            (Some(a), Some(b)) => a == b && a.stackable() && self.name == other.name,
+            _ => false,
+        }
+    }
+
+    /// the name as the player knows it, with the count in front if there's more than one
+    pub fn stack_name(&self, game: &Game) -> String {
+        if self.quantity > 1 {
+            format!("{} x {}", self.quantity, game.item_name(self))
+        } else {
+            game.item_name(self)
+        }
+    }
+
     /// return the distance to another entity
     pub fn distance_to(&self, other: &Entity) -> f32 {
         let dx = other.x - self.x;
@@ -93,7 +269,8 @@ impl Entity {
         ((dx.pow(2) + dy.pow(2)) as f32).sqrt()
     }
 
-    pub fn take_damage(&mut self, damage: i32) {
+    /// the cause reads as "killed by <cause>", e.g. "a kobold" or "lava"
+    pub fn take_damage(&mut self, damage: i32, cause: &str) {
         // apply damage if possible
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
@@ -104,24 +281,26 @@ impl Entity {
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.alive = false;
+                self.killed_by = Some(cause.to_string());
 		fighter.on_death.callback(self);
             }
         }
     }
 
-    pub fn attack(&mut self, target: &mut Entity, game: &mut Game) {
-        let mut damage = self.get_damage(game);
+    pub fn attack(&mut self, target: &mut Entity) {
+        // armour soaks up some of the blow
+        let mut damage = self.get_damage() - target.get_defense();
         //random factor
-        damage += rand::thread_rng().gen_range(-2,4);
+        damage += rng::gen_range(-2,4);
         if damage > 0 {
             // make the target take some damage
-            println!(
+            message!(Combat;
                 "{} attacks {} for {} hit points.",
                 self.name, target.name, damage
             );
-            target.take_damage(damage);
+            target.take_damage(damage, &format!("a {}", self.name));
         } else {
-            println!(
+            message!(Combat;
                 "{} attacks {} but it has no effect!",
                 self.name, target.name
             );
@@ -129,93 +308,51 @@ impl Entity {
     }
 
 
-    //dark magic in order to draw entity on top of map
-    pub fn draw(&self, map: &mut String, seen: &HashSet<(i32, i32)>) -> String {
- 	let mut result = String::with_capacity(map.len());
-	//do nothing if not in fov
-	if !seen.contains(&(self.x,self.y)) {
-		let lines = map.lines();
-	
-		for l in lines {
-		     result.push_str(l);
-		     //linebreak
-		     result.push('\n');
-		}
-        	return result
-		//doesn't work because we made map mutable :(	 
-		//return map 
-	}
-
-	// +1 added to self.y and self.x due to a weird print offset
-        let lines = map.lines();
-	let mut count_l = 0;
-        let mut map_line=String::new();
-	//this gives string slices
-        for l in lines {
-	     count_l += 1;
-	     if count_l == (self.y+1) as usize {
-		//println!("Count_l = y {}", self.y);
-		map_line = l.to_string();
-		break;
-	     }
-	}
-	
-	// based on https://stackoverflow.com/questions/26544542/modifying-chars-in-a-string-by-index?noredirect=1&lq=1
-        let mut count = 0;
-	count_l = 0;
-	let lines = map.lines();
-
-	for l in lines {
-	    count_l += 1;
-	    if count_l != (self.y+1) as usize {
-		result.push_str(l);
-		//linebreak
-		result.push('\n');
-	    }
-	    else {
-		if !map_line.is_empty() {
-            	    let mut chars = map_line.chars();
-
-            	    for c in chars {
-            		count += 1;
-	    		if count == (self.x+1) as usize {
-				result.push(self.char);
-	    		}
-	    		else{
-            			result.push(c);
-	    		}
-           	   }
-    		}
-		//linebreak
-		result.push('\n');
-	    }
-	}
-
-
-    //println!("{}", result);
-
-    result
-    }
-
-    /// heal by the given amount, without going over the maximum
+    /// heal by the given amount, without going over the maximum (which depends on equipment)
     pub fn heal(&mut self, amount: i32) {
+        let max_hp = self.get_max_hp();
         if let Some(ref mut fighter) = self.fighter {
             fighter.hp += amount;
-            if fighter.hp > fighter.max_hp {
-                fighter.hp = fighter.max_hp;
+            if fighter.hp > max_hp {
+                fighter.hp = max_hp;
             }
         }
     }
 
     //alas, no equivalent of Python properties here, we have to do it by hand
-    pub fn get_damage(&self, game: &Game) -> i32 {
+    pub fn get_damage(&self) -> i32 {
         let base_damage = self.fighter.map_or(0, |f| f.base_damage);
         let bonus: i32 = self
-            .get_all_equipped(game)
+            .get_all_equipped()
             .iter()
             .map(|e| e.damage_bonus)
             .sum();
-        base_damage + bonus
+        // it's hard to swing a sword on an empty stomach
+        let weakness = match self.hunger.map(|h| h.state()) {
+            Some(HungerState::Weak) | Some(HungerState::Fainting) | Some(HungerState::Starving) => 1,
+            _ => 0,
+        };
+        base_damage + bonus - weakness
+    }
+
+    pub fn get_defense(&self) -> i32 {
+        let base_defense = self.fighter.map_or(0, |f| f.defense);
+        let bonus: i32 = self
+            .get_all_equipped()
+            .iter()
+            .map(|e| e.defense_bonus)
+            .sum();
+        base_defense + bonus
+    }
+
+    pub fn get_max_hp(&self) -> i32 {
+        let base_max_hp = self.fighter.map_or(0, |f| f.max_hp);
+        let bonus: i32 = self
+            .get_all_equipped()
+            .iter()
+            .map(|e| e.max_hp_bonus)
+            .sum();
+        base_max_hp + bonus
     }
 
     //equipment system
@@ -228,7 +365,7 @@ impl Entity {
         if let Some(ref mut equipment) = self.equipment {
             if !equipment.equipped {
                 equipment.equipped = true;
-                println!("Equipped {} in slot {}.", self.name, equipment.slot);
+                message!("Equipped {} in slot {}.", self.name, equipment.slot);
             }
         } else {
             println!("Can't equip {:?} because it's not an Equipment.", self);
@@ -245,7 +382,7 @@ impl Entity {
         if let Some(ref mut equipment) = self.equipment {
             if equipment.equipped {
                 equipment.equipped = false;
-                println!("Took off {} in slot {}.", self.name, equipment.slot);
+                message!("Took off {} in slot {}.", self.name, equipment.slot);
             }
         } else {
             println!("Can't take off {:?} because it's not an Equipment.", self);
@@ -253,16 +390,8 @@ impl Entity {
     }
 
     /// returns a list of equipped items
-    pub fn get_all_equipped(&self, game: &Game) -> Vec<Equipment> {
-        if self.name == "player" {
-            game.inventory
-                .iter()
-                .filter(|item| item.equipment.map_or(false, |e| e.equipped))
-                .map(|item| item.equipment.unwrap())
-                .collect()
-        } else {
-            vec![] // other entities have no equipment
-        }
+    pub fn get_all_equipped(&self) -> Vec<Equipment> {
+        equipped_items(&self.inventory)
     }
 
 }
@@ -281,9 +410,27 @@ fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut
 }
 
 //these are global functions, not Entity's because uh, Rust borrow weirdness prevents us from using entity as first parameter and entities as the last...
-fn move_by(id: usize, dx: i32, dy: i32, entities: &mut [Entity], game: &mut Game) {
+fn move_by(id: usize, dx: i32, dy: i32, entities: &mut Vec<Entity>, game: &mut Game) {
 	let (x,y) = entities[id].pos();
-	if !game.map[(x + dx) as usize][(y + dy) as usize].blocked {
+	let terrain = game.map[(x + dx) as usize][(y + dy) as usize].terrain;
+	// bumping into a door tries to open it
+	if let Terrain::Door(state) = terrain {
+	    if state != Door::Open {
+	        open_door(id, x + dx, y + dy, entities, game);
+	        return;
+	    }
+	}
+	if terrain == Terrain::DeepWater && !entities[id].swims {
+	    if id == PLAYER {
+	        message!("The water is too deep, you'd drown.");
+	    }
+	    return;
+	}
+	// monsters know better than to jump into lava or chasms
+	if id != PLAYER && (terrain == Terrain::Lava || terrain == Terrain::Chasm) {
+	    return;
+	}
+	if !game.map[(x + dx) as usize][(y + dy) as usize].blocked() {
 	    // try to find an attackable entity there
             let target_id = entities
             .iter()
@@ -292,23 +439,259 @@ fn move_by(id: usize, dx: i32, dy: i32, entities: &mut [Entity], game: &mut Game
 	    match target_id {
 		None => {
 		    // move by the given amount
-		   entities[id].set_pos(x + dx, y + dy)
+		   entities[id].set_pos(x + dx, y + dy);
+		   step_on_terrain(id, entities, game);
+		   if game.map[(x + dx) as usize][(y + dy) as usize].trap.is_some() && entities[id].alive {
+		       spring_trap(id, x + dx, y + dy, entities, game);
+		   }
         	}
         Some(target_id) => {
-            //combat!
-            //println!("Trying to move into npc!");
-	    let (player, target) = mut_two(0, target_id, entities);
-	    player.attack(target, game);
+            //combat! monsters don't fight each other, though
+            if id == PLAYER || target_id == PLAYER {
+                fight(id, target_id, entities, game);
+            }
         	}
 	    }
 	}
 	else {
-		println!("Attempted move into blocked tile!");
+		message!("Attempted move into blocked tile!");
 	}
 }
 
+/// whatever we just stepped into does its thing
+fn step_on_terrain(id: usize, entities: &mut Vec<Entity>, game: &mut Game) {
+    let (x, y) = entities[id].pos();
+    let is_player = id == PLAYER;
+    match game.map[x as usize][y as usize].terrain {
+        Terrain::Water | Terrain::Rubble => {
+            // costs an extra turn
+            entities[id].slowed += 1;
+            if is_player {
+                message!("Moving through here is slow going.");
+            }
+        }
+        Terrain::Lava => {
+            message!(Danger; "{} is burned by the lava!", entities[id].name);
+            hurt(id, 5, "lava", entities);
+        }
+        Terrain::TallGrass => {
+            // trampled down, you can see past it now
+            game.map[x as usize][y as usize] = Tile::new(Terrain::Grass);
+        }
+        Terrain::Chasm if is_player => {
+            message!(Danger; "You fall into the chasm!");
+            entities[PLAYER].take_damage(3, "a fall into a chasm");
+            if entities[PLAYER].alive {
+                let depth = game.dungeon_level + 1;
+                change_level(entities, game, depth);
+                // we didn't come down the stairs, so land somewhere else, but never behind a
+                // locked door: the key was put where you can get to it from the stairs
+                if let Some((x, y)) = reachable_floor(&game.map, entities[PLAYER].pos(), false) {
+                    entities[PLAYER].set_pos(x, y);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// the trap at x, y goes off on whoever is there (or is trying to disarm it)
+fn spring_trap(id: usize, x: i32, y: i32, entities: &mut Vec<Entity>, game: &mut Game) {
+    // a monster setting one off out of sight doesn't give it away
+    let visible = id == PLAYER || player_fov(entities, &game.map).contains(&(x, y));
+    let trap = match game.map[x as usize][y as usize].trap.as_mut() {
+        Some(trap) => trap,
+        None => return,
+    };
+    let kind = trap.kind;
+    if visible {
+        trap.hidden = false;
+        message!(Danger; "{} triggers a {}!", entities[id].name, kind);
+    }
+    match kind {
+        TrapKind::Dart => {
+            let damage = rng::gen_range(2, 6);
+            if visible {
+                message!(Danger; "A dart hits {} for {} hit points.", entities[id].name, damage);
+            }
+            hurt(id, damage, "a dart trap", entities);
+        }
+        TrapKind::Pit => {
+            // falling in hurts, and climbing out takes a while
+            hurt(id, 2, "a pit", entities);
+            entities[id].slowed += 2;
+        }
+        TrapKind::Teleport => {
+            // never behind a locked door, where a player without the key would be stuck for good
+            if let Some((x, y)) = reachable_floor(&game.map, (x, y), true) {
+                entities[id].set_pos(x, y);
+                if visible {
+                    message!("{} is whisked away!", entities[id].name);
+                }
+            }
+        }
+        TrapKind::Alarm => {
+            message!(Danger; "A loud alarm rings out through the level!");
+            for monster in entities.iter_mut() {
+                if monster.ai.is_some() {
+                    monster.ai = Some(Ai::Hunting);
+                }
+            }
+        }
+        TrapKind::PoisonGas => {
+            if visible {
+                message!(Danger; "{} is caught in a cloud of poison gas.", entities[id].name);
+            }
+            entities[id].poisoned += 5;
+        }
+    }
+}
+
+/// poison does a point of damage every turn until it wears off
+fn tick_poison(id: usize, entities: &mut Vec<Entity>) {
+    if entities[id].poisoned > 0 {
+        entities[id].poisoned -= 1;
+        if id == PLAYER {
+            message!(Danger; "You feel sick.");
+        }
+        hurt(id, 1, "poison", entities);
+    }
+}
+
+/// look around for hidden traps next to the player; returns how many were found
+fn search(chance: u32, entities: &[Entity], game: &mut Game) -> u32 {
+    let (px, py) = entities[PLAYER].pos();
+    let mut found = 0;
+    for x in (px - 1)..=(px + 1) {
+        for y in (py - 1)..=(py + 1) {
+            if let Some(ref mut trap) = game.map[x as usize][y as usize].trap {
+                if trap.hidden && rng::gen_range(0, 100) < chance {
+                    trap.hidden = false;
+                    message!(Good; "You find a {}!", trap.kind);
+                    found += 1;
+                }
+            }
+        }
+    }
+    found
+}
+
+/// returns whether there was a trap to try it on (and so whether it took a turn)
+fn disarm_trap(x: i32, y: i32, entities: &mut Vec<Entity>, game: &mut Game) -> bool {
+    match game.map[x as usize][y as usize].trap {
+        Some(trap) if !trap.hidden => {
+            if rng::gen_range(0, 3) > 0 {
+                message!(Good; "You disarm the {}.", trap.kind);
+                game.map[x as usize][y as usize].trap = None;
+            } else {
+                message!(Danger; "You fumble with the {}...", trap.kind);
+                spring_trap(PLAYER, x, y, entities, game);
+            }
+            true
+        }
+        _ => {
+            message!("You don't know of any trap there.");
+            false
+        }
+    }
+}
+
+/// open the door at x, y, if we can; locked doors need a key in the inventory.
+/// Returns whether the door opened (and so whether it took a turn)
+fn open_door(id: usize, x: i32, y: i32, entities: &[Entity], game: &mut Game) -> bool {
+    let tile = &mut game.map[x as usize][y as usize];
+    match tile.door_state() {
+        Some(Door::Closed) => {
+            *tile = Tile::door(Door::Open);
+            message!("{} opens the door.", entities[id].name);
+            true
+        }
+        Some(Door::Locked) if entities[id].inventory.iter().any(|i| i.item == Some(Item::Key)) => {
+            *tile = Tile::door(Door::Open);
+            message!("{} unlocks the door and opens it.", entities[id].name);
+            true
+        }
+        Some(Door::Locked) => {
+            if id == PLAYER {
+                message!("The door is locked.");
+            }
+            false
+        }
+        Some(Door::Open) => {
+            message!("The door is already open.");
+            false
+        }
+        None => {
+            message!("There is no door there.");
+            false
+        }
+    }
+}
+
+/// returns whether the door closed (and so whether it took a turn)
+fn close_door(x: i32, y: i32, entities: &[Entity], game: &mut Game) -> bool {
+    let tile = &mut game.map[x as usize][y as usize];
+    match tile.door_state() {
+        Some(Door::Open) if entities.iter().any(|e| e.pos() == (x, y)) => {
+            message!("Something is in the way.");
+            false
+        }
+        Some(Door::Open) => {
+            *tile = Tile::door(Door::Closed);
+            message!("You close the door.");
+            true
+        }
+        Some(_) => {
+            message!("The door is already closed.");
+            false
+        }
+        None => {
+            message!("There is no door there.");
+            false
+        }
+    }
+}
+
+/// attack, and if that killed a monster, spill whatever it carried on the floor
+fn fight(attacker_id: usize, target_id: usize, entities: &mut Vec<Entity>, game: &mut Game) {
+    // monsters get renamed (and lose their fighter) when they die, so remember what it was
+    let target_name = entities[target_id].name.clone();
+    let target_toughness = entities[target_id].get_max_hp();
+    let (attacker, target) = mut_two(attacker_id, target_id, entities);
+    attacker.attack(target);
+    if !entities[target_id].alive && target_id != PLAYER {
+        if attacker_id == PLAYER {
+            *game.kills.entry(target_name).or_insert(0) += 1;
+            game.xp += target_toughness.max(0) as u32;
+        }
+        drop_all(target_id, entities);
+    }
+}
+
+/// take_damage from anything but a fight (which sees to this itself): a monster that dies
+/// of it still leaves what it carried behind
+fn hurt(id: usize, damage: i32, cause: &str, entities: &mut Vec<Entity>) {
+    entities[id].take_damage(damage, cause);
+    if !entities[id].alive && id != PLAYER {
+        drop_all(id, entities);
+    }
+}
+
+fn drop_all(id: usize, entities: &mut Vec<Entity>) {
+    let (x, y) = entities[id].pos();
+    let dropped: Vec<Entity> = entities[id].inventory.drain(..).collect();
+    for mut item in dropped {
+        if let Some(ref mut equipment) = item.equipment {
+            equipment.equipped = false;
+        }
+        item.set_pos(x, y);
+        message!("A {} falls to the floor.", item.name);
+        put_on_floor(item, entities);
+    }
+}
+
 
-fn move_towards(id: usize, target_x: i32, target_y: i32, entities: &mut [Entity], game: &mut Game) {
+fn move_towards(id: usize, target_x: i32, target_y: i32, entities: &mut Vec<Entity>, game: &mut Game) {
     // vector from this object to the target, and distance
     let dx = target_x - entities[id].x;
     let dy = target_y - entities[id].y;
@@ -332,6 +715,78 @@ struct Fighter {
     on_death: DeathCallback,
 }
 
+// only the player gets hungry for now
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Hunger {
+    nutrition: i32, //goes down by one every turn
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HungerState {
+    Satiated,
+    NotHungry,
+    Hungry,
+    Weak,
+    Fainting,
+    Starving,
+}
+
+const START_NUTRITION: i32 = 900;
+const MAX_NUTRITION: i32 = 1500;
+
+impl Hunger {
+    pub fn state(self) -> HungerState {
+        use HungerState::*;
+        match self.nutrition {
+            n if n > 1000 => Satiated,
+            n if n > 300 => NotHungry,
+            n if n > 100 => Hungry,
+            n if n > 30 => Weak,
+            n if n > 0 => Fainting,
+            _ => Starving,
+        }
+    }
+}
+
+impl std::fmt::Display for HungerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            HungerState::Satiated => write!(f, "Satiated"),
+            HungerState::NotHungry => write!(f, "Not hungry"),
+            HungerState::Hungry => write!(f, "Hungry"),
+            HungerState::Weak => write!(f, "Weak"),
+            HungerState::Fainting => write!(f, "Fainting"),
+            HungerState::Starving => write!(f, "Starving"),
+        }
+    }
+}
+
+/// the player gets a bit hungrier every turn, returns true if they fainted and lose the next turn
+fn tick_hunger(entities: &mut [Entity]) -> bool {
+    let hunger = match entities[PLAYER].hunger.as_mut() {
+        Some(hunger) => hunger,
+        None => return false,
+    };
+    let before = hunger.state();
+    hunger.nutrition -= 1;
+    let after = hunger.state();
+    if after != before {
+        message!("You are now {}.", after.to_string().to_lowercase());
+    }
+    match after {
+        HungerState::Starving => {
+            message!(Danger; "You are starving to death!");
+            entities[PLAYER].take_damage(1, "starvation");
+            false
+        }
+        HungerState::Fainting if rng::gen_range(0, 5) == 0 => {
+            message!(Danger; "You faint from lack of food.");
+            true
+        }
+        _ => false,
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 enum DeathCallback {
     Player,
@@ -351,7 +806,7 @@ impl DeathCallback {
 
 fn player_death(player: &mut Entity) {
     // the game ended!
-    println!("You died!");
+    message!(Danger; "You died!");
 
     // for added effect, transform the player into a corpse!
     player.char = '%';
@@ -360,7 +815,7 @@ fn player_death(player: &mut Entity) {
 fn monster_death(monster: &mut Entity) {
     // transform it into a nasty corpse! it doesn't block, can't be
     // attacked and doesn't move
-    println!("{} is dead!", monster.name);
+    message!(Combat; "{} is dead!", monster.name);
     monster.char = '%';
     monster.blocks = false;
     monster.fighter = None;
@@ -370,21 +825,30 @@ fn monster_death(monster: &mut Entity) {
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 enum Ai{
-    Normal
+    Normal,
+    Hunting, //knows where the player is even when out of sight
 }
 
-fn ai_take_turn(monster_id: usize, entities: &mut [Entity], seen: &HashSet<(i32, i32)>, game: &mut Game) {
+fn ai_take_turn(monster_id: usize, entities: &mut Vec<Entity>, seen: &HashSet<(i32, i32)>, game: &mut Game) {
     // a basic monster takes its turn. If you can see it, it can see you
+    if entities[monster_id].slowed > 0 {
+        // still wading through something
+        entities[monster_id].slowed -= 1;
+        return;
+    }
+    tick_poison(monster_id, entities);
+    if !entities[monster_id].alive {
+        return;
+    }
     let (monster_x, monster_y) = entities[monster_id].pos();
-    if seen.contains(&(monster_x, monster_y)) {
-        if entities[monster_id].distance_to(&entities[0]) >= 2.0 {
+    if seen.contains(&(monster_x, monster_y)) || entities[monster_id].ai == Some(Ai::Hunting) {
+        if entities[monster_id].distance_to(&entities[PLAYER]) >= 2.0 {
             // move towards player if far away
-            let (player_x, player_y) = entities[0].pos();
+            let (player_x, player_y) = entities[PLAYER].pos();
             move_towards(monster_id, player_x, player_y, entities, game);
-        } else if entities[0].fighter.map_or(false, |f| f.hp > 0) {
+        } else if entities[PLAYER].fighter.is_some_and(|f| f.hp > 0) {
             // close enough, attack! (if the player is still alive.)
-            let (monster, player) = mut_two(monster_id, 0, entities);
-            monster.attack(player, game);
+            fight(monster_id, PLAYER, entities, game);
         }
     }
 }
@@ -392,15 +856,58 @@ fn ai_take_turn(monster_id: usize, entities: &mut [Entity], seen: &HashSet<(i32,
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 enum Item {
     Heal, //item type for now
+    Poison, //bad for you, but you won't know until you've drunk one or identified it
+    Identify,
+    Mapping, //shows the whole level
+    Food(i32), //how filling it is
+    Key, //opens locked doors
     Equipment, //generic that enables wearing/taking off
 }
 
+impl Item {
+    /// consumables stack, equipment doesn't
+    pub fn stackable(self) -> bool {
+        self != Item::Equipment
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 /// An object that can be equipped, yielding bonuses.
 struct Equipment {
     slot: Slot,
     equipped: bool,
+    two_handed: bool, //occupies both hands, slot is the main hand
     damage_bonus: i32, //allows negative values
+    defense_bonus: i32,
+    max_hp_bonus: i32,
+}
+
+impl Equipment {
+    /// a piece of equipment in the given slot with no bonuses, fill in what you need
+    pub fn new(slot: Slot) -> Self {
+        Equipment {
+            slot,
+            equipped: false,
+            two_handed: false,
+            damage_bonus: 0,
+            defense_bonus: 0,
+            max_hp_bonus: 0,
+        }
+    }
+
+    /// does this piece take up the given slot? Two-handed weapons take up both hands
+    pub fn occupies(&self, slot: Slot) -> bool {
+        self.slot == slot || (self.two_handed && slot.is_hand())
+    }
+
+    /// all the slots that have to be free before this can be put on
+    pub fn slots_needed(&self) -> Vec<Slot> {
+        if self.two_handed {
+            vec![Slot::RightHand, Slot::LeftHand]
+        } else {
+            vec![self.slot]
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -408,6 +915,28 @@ enum Slot {
     LeftHand,
     RightHand,
     Head,
+    Body,
+    Hands,
+    Feet,
+    Cloak,
+    Amulet,
+    LeftRing,
+    RightRing,
+}
+
+impl Slot {
+    pub fn is_hand(self) -> bool {
+        self == Slot::LeftHand || self == Slot::RightHand
+    }
+
+    /// rings can go on either hand
+    pub fn alternative(self) -> Option<Slot> {
+        match self {
+            Slot::LeftRing => Some(Slot::RightRing),
+            Slot::RightRing => Some(Slot::LeftRing),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Slot {
@@ -416,6 +945,13 @@ impl std::fmt::Display for Slot {
             Slot::LeftHand => write!(f, "left hand"),
             Slot::RightHand => write!(f, "right hand"),
             Slot::Head => write!(f, "head"),
+            Slot::Body => write!(f, "body"),
+            Slot::Hands => write!(f, "hands"),
+            Slot::Feet => write!(f, "feet"),
+            Slot::Cloak => write!(f, "shoulders"),
+            Slot::Amulet => write!(f, "neck"),
+            Slot::LeftRing => write!(f, "left ring finger"),
+            Slot::RightRing => write!(f, "right ring finger"),
         }
     }
 }
@@ -428,90 +964,243 @@ enum UseResult {
 
 fn use_item(
     inventory_id: usize,
-    inventory: &mut Vec<Entity>,
     entities: &mut [Entity],
+    game: &mut Game,
 ) {
     use Item::*;
     // just call the "use_function" if it is defined
-    if let Some(item) = inventory[inventory_id].item {
+    if let Some(item) = entities[PLAYER].inventory[inventory_id].item {
         let on_use = match item {
             Heal => cast_heal,
+            Poison => drink_poison,
+            Identify => cast_identify,
+            Mapping => cast_mapping,
+            Food(_) => eat,
+            Key => use_key,
             Equipment => toggle_equipment,
         };
-        match on_use(inventory_id, entities, inventory) {
+        let name = entities[PLAYER].inventory[inventory_id].name.clone();
+        let result = on_use(inventory_id, entities, game);
+        // using something tells you what it was
+        if let UseResult::UsedUp | UseResult::UsedAndKept = result {
+            if !game.is_identified(&name) {
+                game.identify(&name);
+                message!("That was a {}.", name);
+            }
+        }
+        match result {
             UseResult::UsedUp => {
                 // destroy after use, unless it was cancelled for some reason
-                inventory.remove(inventory_id);
+                // (only one off the stack, though)
+                let inventory = &mut entities[PLAYER].inventory;
+                inventory[inventory_id].quantity -= 1;
+                if inventory[inventory_id].quantity == 0 {
+                    inventory.remove(inventory_id);
+                }
             },
             UseResult::UsedAndKept => {}, // do nothing
             UseResult::Cancelled => {
-                println!("Cancelled");
+                message!("Cancelled");
             }
         }
     } else {
-        println!("The {} cannot be used.", inventory[inventory_id].name);
+        message!("The {} cannot be used.", entities[PLAYER].inventory[inventory_id].name);
     }
 }
 
-fn cast_heal(_inventory_id: usize, entities: &mut [Entity], inventory: &mut [Entity]) -> UseResult {
+fn cast_heal(inventory_id: usize, entities: &mut [Entity], game: &mut Game) -> UseResult {
     // heal the player
-    if let Some(fighter) = entities[0].fighter {
-        if fighter.hp == fighter.max_hp {
-            println!("You are already at full health.");
+    if let Some(fighter) = entities[PLAYER].fighter {
+        // you can't know it's wasted if you don't know what it is
+        let known = game.is_identified(&entities[PLAYER].inventory[inventory_id].name);
+        if known && fighter.hp >= entities[PLAYER].get_max_hp() {
+            message!("You are already at full health.");
             return UseResult::Cancelled;
         }
-        println!("Your wounds start to feel better!");
-        entities[0].heal(4);
+        message!(Good; "Your wounds start to feel better!");
+        entities[PLAYER].heal(4);
         return UseResult::UsedUp;
     }
     UseResult::Cancelled
 }
 
-fn toggle_equipment(
-    inventory_id: usize,
-    entities: &mut [Entity],
-    inventory: &mut [Entity]) -> UseResult {
-        let equipment = match inventory[inventory_id].equipment {
+fn drink_poison(_inventory_id: usize, entities: &mut [Entity], _game: &mut Game) -> UseResult {
+    message!(Danger; "You feel very sick!");
+    entities[PLAYER].poisoned += 5;
+    UseResult::UsedUp
+}
+
+fn cast_mapping(_inventory_id: usize, _entities: &mut [Entity], game: &mut Game) -> UseResult {
+    message!(Good; "A map of the level forms in your mind.");
+    for tile in game.map.iter_mut().flatten() {
+        tile.explored = true;
+    }
+    UseResult::UsedUp
+}
+
+fn use_key(_inventory_id: usize, _entities: &mut [Entity], _game: &mut Game) -> UseResult {
+    message!("Walk into a locked door to unlock it.");
+    UseResult::Cancelled
+}
+
+fn eat(inventory_id: usize, entities: &mut [Entity], _game: &mut Game) -> UseResult {
+    let nutrition = match entities[PLAYER].inventory[inventory_id].item {
+        Some(Item::Food(nutrition)) => nutrition,
+        _ => return UseResult::Cancelled,
+    };
+    if let Some(ref mut hunger) = entities[PLAYER].hunger {
+        if hunger.state() == HungerState::Satiated {
+            message!("You are too full to eat anything more.");
+            return UseResult::Cancelled;
+        }
+        hunger.nutrition = cmp::min(hunger.nutrition + nutrition, MAX_NUTRITION);
+        message!(Good; "That hit the spot. You are now {}.", hunger.state().to_string().to_lowercase());
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+fn cast_identify(inventory_id: usize, entities: &mut [Entity], game: &mut Game) -> UseResult {
+    // the scroll itself is known now, so it won't show up in the list
+    let name = entities[PLAYER].inventory[inventory_id].name.clone();
+    if !game.is_identified(&name) {
+        message!("This is a {}!", name);
+        game.identify(&name);
+    }
+    let unknown: Vec<usize> = entities[PLAYER]
+        .inventory
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !game.is_identified(&item.name))
+        .map(|(id, _)| id)
+        .collect();
+    if unknown.is_empty() {
+        message!("You have nothing left to identify.");
+        return UseResult::UsedUp;
+    }
+    let names: Vec<String> = unknown
+        .iter()
+        .map(|&id| game.item_name(&entities[PLAYER].inventory[id]))
+        .collect();
+    match menu("Identify which item?\n", &names) {
+        Some(index) => {
+            let item = &entities[PLAYER].inventory[unknown[index]];
+            message!("The {} is a {}.", game.item_name(item), item.name);
+            game.identify(&item.name);
+        }
+        None => message!("The scroll crumbles to dust unread."),
+    }
+    UseResult::UsedUp
+}
+
+fn toggle_equipment(inventory_id: usize, entities: &mut [Entity], _game: &mut Game) -> UseResult {
+    let inventory = &mut entities[PLAYER].inventory;
+    let mut equipment = match inventory[inventory_id].equipment {
         Some(equipment) => equipment,
         None => return UseResult::Cancelled,
     };
     if equipment.equipped {
-        //note: if we were using a game.log (as is the case in a usual roguelike),
-        // game is already borrowed here (in game.inventory)
         inventory[inventory_id].take_off();
     } else {
-        // if the slot is already being used, take off whatever is there first
-        if let Some(current) = get_equipped_in_slot(equipment.slot, &inventory) {
-            inventory[current].take_off();
+        // rings go on the other hand if this one is taken and the other is free
+        if let Some(other) = equipment.slot.alternative() {
+            if get_equipped_in_slot(equipment.slot, inventory).is_some()
+                && get_equipped_in_slot(other, inventory).is_none()
+            {
+                equipment.slot = other;
+                inventory[inventory_id].equipment = Some(equipment);
+            }
+        }
+        // if the slots are already being used, take off whatever is there first
+        // (a two-handed weapon needs both hands free)
+        for slot in equipment.slots_needed() {
+            if let Some(current) = get_equipped_in_slot(slot, inventory) {
+                inventory[current].take_off();
+            }
         }
         inventory[inventory_id].equip();
     }
+    // taking off a max HP bonus can leave us above the new maximum
+    entities[PLAYER].heal(0);
     UseResult::UsedAndKept
-    }
+}
 
 /// add to the player's inventory and remove from the map
 fn pick_item_up(
     object_id: usize,
     entities: &mut Vec<Entity>,
-    inventory: &mut Vec<Entity>,
+    game: &Game,
 ) {
-    if inventory.len() >= 26 {
+    // if we already have some of these, it just goes on the stack
+    let stack_id = entities[PLAYER]
+        .inventory
+        .iter()
+        .position(|i| i.stacks_with(&entities[object_id]));
+    if let Some(stack_id) = stack_id {
+        let item = entities.swap_remove(object_id);
+        message!("You picked up {}!", item.stack_name(game));
+        let stack = &mut entities[PLAYER].inventory[stack_id];
+        stack.quantity += item.quantity;
+        message!("You now have {}.", stack.stack_name(game));
+    } else if entities[PLAYER].inventory.len() >= 26 {
 	//println! is effectively equal to format!
-        println!("Your inventory is full, cannot pick up {}.",
-                entities[object_id].name);
+        message!("Your inventory is full, cannot pick up {}.",
+                game.item_name(&entities[object_id]));
     } else {
         let item = entities.swap_remove(object_id);
-        println!("You picked up a {}!", item.name);
-        inventory.push(item);
+        message!("You picked up {}!", item.stack_name(game));
+        entities[PLAYER].inventory.push(item);
+    }
+}
+
+/// how many to take out of a stack of `quantity`: nothing typed means all of them, and anything
+/// that isn't a number in range means the player changed their mind
+fn parse_amount(answer: &str, quantity: u32) -> Option<u32> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Some(quantity);
     }
+    answer.parse().ok().filter(|amount| (1..=quantity).contains(amount))
 }
 
+/// take an item out of the player's inventory and put it on the floor underfoot,
+/// splitting the stack if we only drop some of it
+fn drop_item(inventory_id: usize, amount: u32, entities: &mut Vec<Entity>, game: &Game) {
+    let stack = &mut entities[PLAYER].inventory[inventory_id];
+    let mut item = if amount < stack.quantity {
+        stack.quantity -= amount;
+        let mut part = stack.clone();
+        part.quantity = amount;
+        part
+    } else {
+        entities[PLAYER].inventory.remove(inventory_id)
+    };
+    if item.equipment.is_some_and(|e| e.equipped) {
+        item.take_off();
+        // in case it was giving us extra HP
+        entities[PLAYER].heal(0);
+    }
+    let (x, y) = entities[PLAYER].pos();
+    item.set_pos(x, y);
+    message!("You dropped {}.", item.stack_name(game));
+    put_on_floor(item, entities);
+}
+
+/// leave an item where it's been put, on top of a stack of the same thing if there's one there
+fn put_on_floor(item: Entity, entities: &mut Vec<Entity>) {
+    match entities.iter_mut().find(|e| e.pos() == item.pos() && e.stacks_with(&item)) {
+        Some(stack) => stack.quantity += item.quantity,
+        None => entities.push(item),
+    }
+}
+
+/// which item (if any) takes up the slot, counting two-handed weapons in both hands
 fn get_equipped_in_slot(slot: Slot, inventory: &[Entity]) -> Option<usize> {
     for (inventory_id, item) in inventory.iter().enumerate() {
         if item
             .equipment
             .as_ref()
-            .map_or(false, |e| e.equipped && e.slot == slot)
+            .is_some_and(|e| e.equipped && e.occupies(slot))
         {
             return Some(inventory_id);
         }
@@ -519,36 +1208,596 @@ fn get_equipped_in_slot(slot: Slot, inventory: &[Entity]) -> Option<usize> {
     None
 }
 
-fn make_map() -> Map {
-    // fill map with "unblocked" tiles
-    let mut map = vec![vec![Tile::empty(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+/// returns a list of equipped items in the given inventory
+fn equipped_items(inventory: &[Entity]) -> Vec<Equipment> {
+    inventory
+        .iter()
+        .filter_map(|item| item.equipment)
+        .filter(|e| e.equipped)
+        .collect()
+}
 
-    //place walls around
-    //Rust is weird, ranges are inclusive at the beginning but exclusive at the end
-    for x in 0 ..MAP_WIDTH{
+#[test]
+fn two_handed_tests() {
+    let mut player = test_player(1, 1);
+
+    let mut shield = Entity::new(0, 0, '[', "shield");
+    shield.item = Some(Item::Equipment);
+    shield.equipment = Some(Equipment{defense_bonus: 1, ..Equipment::new(Slot::LeftHand)});
+    let mut greatsword = Entity::new(0, 0, '/', "greatsword");
+    greatsword.item = Some(Item::Equipment);
+    greatsword.equipment = Some(Equipment{two_handed: true, ..Equipment::new(Slot::RightHand)});
+    player.inventory = vec![shield, greatsword];
+    let mut entities = vec![player];
+    let mut game = Game::new();
+
+    toggle_equipment(0, &mut entities, &mut game);
+    assert_eq!(get_equipped_in_slot(Slot::LeftHand, &entities[PLAYER].inventory), Some(0));
+    assert_eq!(entities[PLAYER].get_defense(), 1);
+
+    // the greatsword pushes the shield out of the left hand
+    toggle_equipment(1, &mut entities, &mut game);
+    let inventory = &entities[PLAYER].inventory;
+    assert_eq!(get_equipped_in_slot(Slot::LeftHand, inventory), Some(1));
+    assert_eq!(get_equipped_in_slot(Slot::RightHand, inventory), Some(1));
+    assert!(!inventory[0].equipment.unwrap().equipped);
+
+    // and the shield pushes the greatsword out of both
+    toggle_equipment(0, &mut entities, &mut game);
+    assert_eq!(get_equipped_in_slot(Slot::RightHand, &entities[PLAYER].inventory), None);
+}
+
+#[test]
+fn stack_tests() {
+    assert_eq!(parse_amount("", 5), Some(5));
+    assert_eq!(parse_amount(" 2 ", 5), Some(2));
+    // a typo shouldn't drop the whole stack
+    assert_eq!(parse_amount("2w", 5), None);
+    assert_eq!(parse_amount("0", 5), None);
+    assert_eq!(parse_amount("6", 5), None);
+
+    let player = Entity::new(1, 1, '@', "Player");
+    let mut potion = Entity::new(1, 1, '!', "healing potion");
+    potion.item = Some(Item::Heal);
+    let mut more_potions = potion.clone();
+    more_potions.quantity = 2;
+    let mut sword = Entity::new(1, 1, '/', "sword");
+    sword.item = Some(Item::Equipment);
+    sword.equipment = Some(Equipment::new(Slot::RightHand));
+    let mut entities = vec![player, potion, more_potions, sword.clone(), sword];
+    let game = Game::new();
+
+    // the potions merge, the swords don't
+    while entities.len() > 1 {
+        pick_item_up(1, &mut entities, &game);
+    }
+    assert_eq!(entities[PLAYER].inventory.len(), 3);
+    let potion_id = entities[PLAYER].inventory.iter().position(|i| i.item == Some(Item::Heal)).unwrap();
+    assert_eq!(entities[PLAYER].inventory[potion_id].quantity, 3);
+
+    // dropping part of the stack splits it
+    drop_item(potion_id, 2, &mut entities, &game);
+    assert_eq!(entities[PLAYER].inventory[potion_id].quantity, 1);
+    assert_eq!(entities[1].quantity, 2);
+    // and dropping the rest goes back on that pile instead of starting another
+    drop_item(potion_id, 1, &mut entities, &game);
+    assert_eq!(entities.len(), 2);
+    assert_eq!(entities[1].quantity, 3);
+}
+
+#[test]
+fn identify_tests() {
+    let mut game = Game::new();
+    let mut potion = Entity::new(1, 1, '!', "healing potion");
+    potion.item = Some(Item::Heal);
+    let sword = Entity::new(1, 1, '/', "sword");
+
+    assert!(game.item_name(&potion).ends_with(" potion"));
+    assert_ne!(game.item_name(&potion), "healing potion");
+    // things that don't need identifying are always known
+    assert_eq!(game.item_name(&sword), "sword");
+
+    game.identify("healing potion");
+    assert_eq!(game.item_name(&potion), "healing potion");
+
+    // every kind gets a look of its own, and which look goes with which kind changes between games
+    assert!(POTION_LOOKS.len() >= POTION_NAMES.len());
+    let mut healing_looks = HashSet::new();
+    for seed in 0..20 {
+        rng::reseed(seed);
+        let appearances = random_appearances();
+        let looks: HashSet<&String> = appearances.values().collect();
+        assert_eq!(looks.len(), POTION_NAMES.len() + SCROLL_NAMES.len());
+        healing_looks.insert(appearances["healing potion"].clone());
+    }
+    assert!(healing_looks.len() > 1);
+}
+
+#[test]
+fn hunger_tests() {
+    let mut player = test_player(1, 1);
+    player.fighter.as_mut().unwrap().base_damage = 3;
+    player.hunger = Some(Hunger { nutrition: 32 });
+    let mut entities = vec![player];
+
+    tick_hunger(&mut entities);
+    assert_eq!(entities[PLAYER].hunger.unwrap().state(), HungerState::Weak);
+    assert_eq!(entities[PLAYER].get_damage(), 2);
+
+    // starvation hurts
+    entities[PLAYER].hunger = Some(Hunger { nutrition: 0 });
+    tick_hunger(&mut entities);
+    assert_eq!(entities[PLAYER].fighter.unwrap().hp, 9);
+}
+
+#[test]
+fn door_tests() {
+    let mut game = Game::new();
+    game.map[3][3] = Tile::door(Door::Locked);
+    let mut entities = vec![Entity::new(2, 3, '@', "Player")];
+
+    // locked without a key
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert_eq!(game.map[3][3].door_state(), Some(Door::Locked));
+    assert!(game.map[3][3].block_sight());
+
+    let mut key = Entity::new(0, 0, '-', "key");
+    key.item = Some(Item::Key);
+    entities[PLAYER].inventory.push(key);
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert_eq!(game.map[3][3].door_state(), Some(Door::Open));
+    assert!(!game.map[3][3].blocked() && !game.map[3][3].block_sight());
+    // opening took the move
+    assert_eq!(entities[PLAYER].pos(), (2, 3));
+
+    assert!(close_door(3, 3, &entities, &mut game));
+    assert_eq!(game.map[3][3].door_state(), Some(Door::Closed));
+    // no door, no turn spent
+    assert!(!close_door(2, 3, &entities, &mut game));
+}
+
+#[test]
+fn terrain_tests() {
+    let mut game = Game::new();
+    game.map[3][3] = Tile::new(Terrain::Water);
+    game.map[3][4] = Tile::new(Terrain::DeepWater);
+    game.map[4][3] = Tile::new(Terrain::TallGrass);
+    let mut entities = vec![test_player(2, 3)];
+
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert_eq!(entities[PLAYER].pos(), (3, 3));
+    assert_eq!(entities[PLAYER].slowed, 1);
+
+    // too deep for us
+    move_by(PLAYER, 0, 1, &mut entities, &mut game);
+    assert_eq!(entities[PLAYER].pos(), (3, 3));
+
+    // tall grass blocks sight until trampled
+    assert!(game.map[4][3].block_sight());
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert!(!game.map[4][3].block_sight());
+
+    // a corridor too narrow for a lava blob to go anywhere without blocking it
+    let mut corridor = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    for x in 1..MAP_WIDTH - 1 {
+        corridor[x as usize][5] = Tile::empty();
+    }
+    assert!(connected(&corridor));
+    rng::reseed(33);
+    let mut map = corridor.clone();
+    for _ in 0..10 {
+        scatter(&mut map, Terrain::Lava, 1);
+        scatter(&mut map, Terrain::Chasm, 0);
+        assert!(connected(&map));
+    }
+    corridor[6][5] = Tile::new(Terrain::Lava);
+    assert!(!connected(&corridor));
+
+    // falling down a chasm lands you where the key can be reached from, not behind a locked door
+    game.map[4][3] = Tile::new(Terrain::Chasm);
+    entities[PLAYER].set_pos(3, 3);
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert_eq!(game.dungeon_level, 2);
+    let stairs = find_terrain(&game.map, Terrain::UpStairs).unwrap();
+    assert!(reachable(&game.map, stairs, false).contains(&entities[PLAYER].pos()));
+}
+
+#[test]
+fn trap_tests() {
+    let mut game = Game::new();
+    for tile in game.map.iter_mut().flatten() {
+        tile.trap = None;
+    }
+    game.map[3][3] = Tile::empty();
+    game.map[3][3].trap = Some(Trap { kind: TrapKind::PoisonGas, hidden: true });
+    let mut entities = vec![test_player(2, 3)];
+
+    assert_eq!(search(100, &entities, &mut game), 1);
+    assert!(!game.map[3][3].trap.unwrap().hidden);
+    assert_eq!(game.map[3][3].glyph(), '^');
+
+    move_by(PLAYER, 1, 0, &mut entities, &mut game);
+    assert_eq!(entities[PLAYER].poisoned, 5);
+    tick_poison(PLAYER, &mut entities);
+    assert_eq!(entities[PLAYER].fighter.unwrap().hp, 9);
+
+    // a teleport never drops you behind a locked door
+    fill_map(&mut game.map, Tile::wall());
+    for x in 1..6 {
+        game.map[x][1] = Tile::empty();
+        game.map[x][3] = Tile::empty();
+    }
+    game.map[3][2] = Tile::door(Door::Locked);
+    game.map[5][1].trap = Some(Trap { kind: TrapKind::Teleport, hidden: false });
+    for _ in 0..20 {
+        spring_trap(PLAYER, 5, 1, &mut entities, &mut game);
+        assert_eq!(entities[PLAYER].y, 1);
+    }
+
+    // a monster setting off a trap only gives it away if you can see it happen
+    entities[PLAYER].set_pos(1, 1);
+    entities.push(make_kobold(5, 3, 3));
+    game.map[5][3].trap = Some(Trap { kind: TrapKind::Pit, hidden: true });
+    spring_trap(1, 5, 3, &mut entities, &mut game);
+    assert!(game.map[5][3].trap.unwrap().hidden);
+    entities[PLAYER].set_pos(3, 3);
+    spring_trap(1, 5, 3, &mut entities, &mut game);
+    assert!(!game.map[5][3].trap.unwrap().hidden);
+
+    // whatever kills a monster, what it carried ends up on the floor
+    let mut cap = Entity::new(5, 3, '[', "leather cap");
+    cap.item = Some(Item::Equipment);
+    cap.equipment = Some(Equipment { equipped: true, ..Equipment::new(Slot::Head) });
+    entities[1].inventory = vec![cap];
+    entities[1].fighter.as_mut().unwrap().hp = 1;
+    entities[1].poisoned = 1;
+    tick_poison(1, &mut entities);
+    assert!(!entities[1].alive && entities[1].inventory.is_empty());
+    let dropped = entities.iter().find(|e| e.name == "leather cap").unwrap();
+    assert_eq!(dropped.pos(), (5, 3));
+    assert!(!dropped.equipment.unwrap().equipped);
+}
+
+#[test]
+fn hud_tests() {
+    let game = Game::new();
+    let mut player = test_player(1, 1);
+    player.name = "Bob".into();
+    player.poisoned = 3;
+    let mut seen_kobold = make_kobold(2, 1, 3);
+    seen_kobold.fighter.as_mut().unwrap().hp = 2;
+    let hidden_kobold = make_kobold(9, 9, 3);
+    let entities = vec![player, seen_kobold, hidden_kobold];
+    let seen: HashSet<(i32, i32)> = vec![(1, 1), (2, 1)].into_iter().collect();
+
+    let lines = sidebar(&entities, &game, &seen);
+    assert_eq!(lines[0], "Bob");
+    assert!(lines.contains(&"Poisoned (3)".to_string()));
+    let monsters: Vec<&String> = lines.iter().skip_while(|l| *l != "In view:").skip(1).collect();
+    assert_eq!(monsters.len(), 1);
+    assert!(monsters[0].ends_with("(nearly dead)"));
+    assert_eq!(health_state(7, 10), "lightly wounded");
+
+    // a shield isn't a weapon, a blunt stick still is
+    let mut player = entities[PLAYER].clone();
+    assert_eq!(weapon_name(&player, &game), "bare hands");
+    let mut shield = Entity::new(0, 0, '[', "shield");
+    shield.item = Some(Item::Equipment);
+    shield.equipment = Some(Equipment { equipped: true, defense_bonus: 1, ..Equipment::new(Slot::LeftHand) });
+    player.inventory.push(shield);
+    assert_eq!(weapon_name(&player, &game), "bare hands");
+    let mut stick = Entity::new(0, 0, '/', "stick");
+    stick.item = Some(Item::Equipment);
+    stick.equipment = Some(Equipment { equipped: true, damage_bonus: -1, ..Equipment::new(Slot::RightHand) });
+    player.inventory.push(stick);
+    assert_eq!(weapon_name(&player, &game), "stick");
+}
+
+#[test]
+fn level_tests() {
+    let mut game = Game::new();
+    let mut entities = vec![Entity::new(1, 1, '@', "Player"), make_ration(2, 2)];
+    let first_map: Vec<Vec<Terrain>> = game.map.iter().map(|c| c.iter().map(|t| t.terrain).collect()).collect();
+    assert!(find_terrain(&game.map, Terrain::UpStairs).is_none());
+
+    change_level(&mut entities, &mut game, 2);
+    assert_eq!(game.dungeon_level, 2);
+    assert_eq!(entities[PLAYER].pos(), find_terrain(&game.map, Terrain::UpStairs).unwrap());
+    assert!(!entities.iter().any(|e| e.name == "food ration" && e.pos() == (2, 2)));
+
+    change_level(&mut entities, &mut game, 1);
+    assert_eq!(game.dungeon_level, 1);
+    let back_map: Vec<Vec<Terrain>> = game.map.iter().map(|c| c.iter().map(|t| t.terrain).collect()).collect();
+    assert_eq!(first_map, back_map);
+    assert_eq!(entities.len(), 2);
+    assert_eq!(entities[1].pos(), (2, 2));
+    assert_eq!(entities[PLAYER].pos(), find_terrain(&game.map, Terrain::DownStairs).unwrap());
+    assert!(game.levels.contains_key(&2));
+
+    // a level we cleared out stays empty when we come back
+    game.levels.get_mut(&2).unwrap().entities.clear();
+    change_level(&mut entities, &mut game, 2);
+    assert_eq!(entities.len(), 1);
+}
+
+fn make_map(depth: u32) -> Map {
+    // fill map with "unblocked" tiles
+    let mut map = vec![vec![Tile::empty(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+
+    //place walls around
+    //Rust is weird, ranges are inclusive at the beginning but exclusive at the end
+    for x in 0 ..MAP_WIDTH{
     	map[x as usize][0] = Tile::wall();
-	map[x as usize][(MAP_WIDTH-1) as usize] = Tile::wall();
+	map[x as usize][(MAP_HEIGHT-1) as usize] = Tile::wall();
     }
     for y in 0 ..MAP_HEIGHT{
     	map[0][y as usize] = Tile::wall();
 	map[(MAP_WIDTH-1) as usize][y as usize] = Tile::wall();
     }
 
-    // place two pillars to test the map
-    map[10][12] = Tile::wall();
-    map[5][12] = Tile::wall();
+    // a few walled-off rooms, keeping the corner where the player starts clear
+    let mut rooms = vec![Rect::new(0, 0, 7, 7)];
+    for _ in 0..MAX_ROOM_TRIES {
+        let w = rng::gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let h = rng::gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        // leave a gap between the room and the outer walls, so you can walk around it
+        let x = rng::gen_range(2, MAP_WIDTH - w - 1);
+        let y = rng::gen_range(2, MAP_HEIGHT - h - 1);
+        let new_room = Rect::new(x, y, w, h);
+        if !rooms.iter().any(|other| new_room.intersects_with(other)) {
+            create_room(new_room, &mut map);
+            rooms.push(new_room);
+        }
+    }
 
-    //stairs in random place
-    let x = rand::thread_rng().gen_range(1,18);
-    let y = rand::thread_rng().gen_range(1,18);
+    // some scenery
+    let ponds = rng::gen_range(0, 3);
+    for _ in 0..ponds {
+        let center = scatter(&mut map, Terrain::Water, 2);
+        deepen(&mut map, center);
+    }
+    for _ in 0..rng::gen_range(1, 4) {
+        scatter(&mut map, Terrain::TallGrass, 2);
+    }
+    for _ in 0..rng::gen_range(0, 3) {
+        scatter(&mut map, Terrain::Rubble, 1);
+    }
+    if rng::gen_range(0, 3) == 0 {
+        scatter(&mut map, Terrain::Lava, 1);
+    }
+    if rng::gen_range(0, 4) == 0 {
+        scatter(&mut map, Terrain::Chasm, 0);
+    }
+
+    // hidden traps, but not right where the player starts
+    for _ in 0..rng::gen_range(2, 5) {
+        let (x, y) = random_floor(&map);
+        if x < 3 && y < 3 {
+            continue;
+        }
+        let kind = match rng::gen_range(0, 5) {
+            0 => TrapKind::Dart,
+            1 => TrapKind::Pit,
+            2 => TrapKind::Teleport,
+            3 => TrapKind::Alarm,
+            _ => TrapKind::PoisonGas,
+        };
+        map[x as usize][y as usize].trap = Some(Trap { kind, hidden: true });
+    }
 
-    map[x][y] = Tile::stairs();
+    //stairs in random places, the first level has no way up
+    let (x, y) = random_floor(&map);
+    map[x as usize][y as usize] = Tile::new(Terrain::DownStairs);
+    if depth > 1 {
+        let (x, y) = random_floor(&map);
+        map[x as usize][y as usize] = Tile::new(Terrain::UpStairs);
+    }
 
     map
 }
 
+/// where on the map the given kind of terrain is, if anywhere
+fn find_terrain(map: &Map, terrain: Terrain) -> Option<(i32, i32)> {
+    for (x, column) in map.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if tile.terrain == terrain {
+                return Some((x as i32, y as i32));
+            }
+        }
+    }
+    None
+}
+
+/// terrain you can't (or really shouldn't) walk through
+fn hazard(terrain: Terrain) -> bool {
+    matches!(terrain, Terrain::Lava | Terrain::DeepWater | Terrain::Chasm)
+}
+
+/// can you still get everywhere on foot, going through doors but around hazards?
+fn connected(map: &Map) -> bool {
+    let passable = |x: i32, y: i32| {
+        (0..MAP_WIDTH).contains(&x) && (0..MAP_HEIGHT).contains(&y) && {
+            let terrain = map[x as usize][y as usize].terrain;
+            terrain != Terrain::Wall && !hazard(terrain)
+        }
+    };
+    let all: Vec<(i32, i32)> = (0..MAP_WIDTH).flat_map(|x| (0..MAP_HEIGHT).map(move |y| (x, y))).filter(|&(x, y)| passable(x, y)).collect();
+    let start = match all.first() {
+        Some(&start) => start,
+        None => return true,
+    };
+    let mut reached = HashSet::new();
+    reached.insert(start);
+    let mut todo = vec![start];
+    while let Some((x, y)) = todo.pop() {
+        for &(nx, ny) in &[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+            if passable(nx, ny) && reached.insert((nx, ny)) {
+                todo.push((nx, ny));
+            }
+        }
+    }
+    reached.len() == all.len()
+}
+
+/// a rough blob of terrain around a random floor tile, returns the center.
+/// A hazard that would cut off part of the level (say, lava filling a corridor) isn't placed at all
+fn scatter(map: &mut Map, terrain: Terrain, radius: i32) -> (i32, i32) {
+    let before = if hazard(terrain) { Some(map.clone()) } else { None };
+    let (cx, cy) = random_floor(map);
+    for x in (cx - radius)..=(cx + radius) {
+        for y in (cy - radius)..=(cy + radius) {
+            if x < 1 || y < 1 || x >= MAP_WIDTH - 1 || y >= MAP_HEIGHT - 1 {
+                continue;
+            }
+            // ragged edges look more natural
+            let edge = (x - cx).abs() == radius || (y - cy).abs() == radius;
+            if edge && rng::gen_range(0, 2) == 0 {
+                continue;
+            }
+            // don't drown walls, doors or the starting corner
+            if map[x as usize][y as usize].terrain == Terrain::Floor && !(x < 3 && y < 3) {
+                map[x as usize][y as usize] = Tile::new(terrain);
+            }
+        }
+    }
+    if let Some(before) = before {
+        if !connected(map) {
+            *map = before;
+        }
+    }
+    (cx, cy)
+}
+
+/// the middle of a pond is too deep to wade through, as long as that can't cut off part of the level
+fn deepen(map: &mut Map, (cx, cy): (i32, i32)) {
+    for &(x, y) in &[(cx, cy), (cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+        // only out in the open, at least two tiles from any wall or door
+        let open = ((x - 2)..=(x + 2)).all(|nx| {
+            ((y - 2)..=(y + 2)).all(|ny| {
+                nx >= 0 && ny >= 0 && nx < MAP_WIDTH && ny < MAP_HEIGHT && {
+                    !matches!(map[nx as usize][ny as usize].terrain, Terrain::Wall | Terrain::Door(_))
+                }
+            })
+        });
+        if open && map[x as usize][y as usize].terrain == Terrain::Water {
+            map[x as usize][y as usize] = Tile::new(Terrain::DeepWater);
+            // belt and braces: it's only shallow water if it would cut anything off after all
+            if !connected(map) {
+                map[x as usize][y as usize] = Tile::new(Terrain::Water);
+            }
+        }
+    }
+}
+
+/// wall off the rectangle and put a door or two in the walls
+fn create_room(room: Rect, map: &mut Map) {
+    for x in room.x1..=room.x2 {
+        map[x as usize][room.y1 as usize] = Tile::wall();
+        map[x as usize][room.y2 as usize] = Tile::wall();
+    }
+    for y in room.y1..=room.y2 {
+        map[room.x1 as usize][y as usize] = Tile::wall();
+        map[room.x2 as usize][y as usize] = Tile::wall();
+    }
+
+    // some rooms are locked
+    let state = if rng::gen_range(0, 3) == 0 { Door::Locked } else { Door::Closed };
+    let doors = rng::gen_range(1, 3);
+    for _ in 0..doors {
+        // anywhere along a wall except the corners
+        let (x, y) = match rng::gen_range(0, 4) {
+            0 => (rng::gen_range(room.x1 + 1, room.x2), room.y1),
+            1 => (rng::gen_range(room.x1 + 1, room.x2), room.y2),
+            2 => (room.x1, rng::gen_range(room.y1 + 1, room.y2)),
+            _ => (room.x2, rng::gen_range(room.y1 + 1, room.y2)),
+        };
+        map[x as usize][y as usize] = Tile::door(state);
+    }
+}
+
+/// a random spot that can be walked on and doesn't have anything special
+fn random_floor(map: &Map) -> (i32, i32) {
+    loop {
+        let x = rng::gen_range(1, MAP_WIDTH - 1);
+        let y = rng::gen_range(1, MAP_HEIGHT - 1);
+        let tile = map[x as usize][y as usize];
+        if tile.terrain == Terrain::Floor && tile.trap.is_none() {
+            return (x, y);
+        }
+    }
+}
+
+/// Everywhere you can walk to from the start, without a key: through closed doors if
+/// `through_doors`, otherwise without going through any door at all.
+fn reachable(map: &Map, start: (i32, i32), through_doors: bool) -> Vec<(i32, i32)> {
+    // flood fill from the start
+    let mut reachable = vec![start];
+    let mut visited = HashSet::new();
+    visited.insert(start);
+    let mut i = 0;
+    while i < reachable.len() {
+        let (x, y) = reachable[i];
+        for &(dx, dy) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let tile = map[(x + dx) as usize][(y + dy) as usize];
+            let safe = match tile.terrain {
+                Terrain::Door(Door::Locked) => false,
+                Terrain::Door(_) => through_doors,
+                terrain => terrain != Terrain::Wall && !hazard(terrain),
+            };
+            if safe && visited.insert((x + dx, y + dy)) {
+                reachable.push((x + dx, y + dy));
+            }
+        }
+        i += 1;
+    }
+    reachable
+}
+
+/// like random_floor, but somewhere you can walk to from the start; None if there's nowhere
+fn reachable_floor(map: &Map, start: (i32, i32), through_doors: bool) -> Option<(i32, i32)> {
+    let spots: Vec<(i32, i32)> = reachable(map, start, through_doors)
+        .into_iter()
+        .filter(|&(x, y)| map[x as usize][y as usize].terrain == Terrain::Floor && map[x as usize][y as usize].trap.is_none())
+        .collect();
+    if spots.is_empty() {
+        None
+    } else {
+        Some(spots[rng::gen_range(0, spots.len())])
+    }
+}
+
+/// if the level has locked doors, a key somewhere you can walk to without going through any door
+fn make_key(map: &Map, start: (i32, i32)) -> Option<Entity> {
+    let locked = map.iter().flatten().any(|t| t.door_state() == Some(Door::Locked));
+    if !locked {
+        return None;
+    }
+    let reachable = reachable(map, start, false);
+    let (x, y) = reachable[rng::gen_range(0, reachable.len())];
+    let mut key = Entity::new(x, y, '-', "key");
+    key.item = Some(Item::Key);
+    key.description = Some("A heavy iron key. It should fit the locked doors on this level.".into());
+    Some(key)
+}
+
 
 //GUI
+/// print the prompt and read a line of input, without the line break
+fn prompt_line(prompt: &str) -> String {
+    term::read_text(prompt)
+}
+
+/// ask for one of the movement directions, None if the answer wasn't one
+fn prompt_direction() -> Option<(i32, i32)> {
+    match prompt_line("Which direction (w/e/n/s)? ").trim() {
+        "w" => Some((-1, 0)),
+        "e" => Some((1, 0)),
+        "n" => Some((0, -1)),
+        "s" => Some((0, 1)),
+        _ => None,
+    }
+}
+
 fn menu<T: AsRef<str>>(header: &str, options: &[T]) -> Option<usize> {
     assert!(
         options.len() <= 26,
@@ -564,26 +1813,17 @@ fn menu<T: AsRef<str>>(header: &str, options: &[T]) -> Option<usize> {
     // print all the options
     for (index, option_text) in options.iter().enumerate() {
         let menu_letter = (b'a' + index as u8) as char;
-        let text = println!("({}) {}", menu_letter, option_text.as_ref());
+        println!("({}) {}", menu_letter, option_text.as_ref());
     }
 
     // convert the ASCII code to an index; if it corresponds to an option, return it Option<usize>
-       use std::io::{stdin,stdout};
-       use PlayerAction::*;
-
-       let mut s=String::new();
-       print!("Please enter letter: ");
-       let _=stdout().flush();
-       stdin().read_line(&mut s).expect("Did not enter a correct letter");
-       if let Some('\n')=s.chars().next_back() {
-	   s.pop();
-       }
-       if let Some('\r')=s.chars().next_back() {
-	   s.pop();
-       }
-       println!("You typed: {}",s);
+       let s = term::read_command("Please enter letter: ")?;
        //lots of dark magic here, thanks Rust for making it difficult to get chars out of a string
-       let c = s[0..].chars().next().unwrap();
+       //anything that isn't a letter (including an empty line) cancels
+       let c = match s.chars().next() {
+            Some(c) if c.is_ascii_lowercase() => c,
+            _ => return None,
+       };
        let index = c as usize - 'a' as usize;
        //println!("Index is: {}", index);
        if index < options.len() {
@@ -593,9 +1833,9 @@ fn menu<T: AsRef<str>>(header: &str, options: &[T]) -> Option<usize> {
        }
 }
 
-fn inventory_menu(inventory: &[Entity], header: &str) -> Option<usize> {
+fn inventory_menu(inventory: &[Entity], header: &str, game: &Game) -> Option<usize> {
     // show a menu with each item of the inventory as an option
-    let options = if inventory.len() == 0 {
+    let options = if inventory.is_empty() {
         vec!["Inventory is empty.".into()]
     } else {
         inventory
@@ -603,10 +1843,13 @@ fn inventory_menu(inventory: &[Entity], header: &str) -> Option<usize> {
         .map(|item| {
             // show additional information, in case it's equipped
             match item.equipment {
+                Some(equipment) if equipment.equipped && equipment.two_handed => {
+                    format!("{} (in both hands)", item.name)
+                }
                 Some(equipment) if equipment.equipped => {
                     format!("{} (on {})", item.name, equipment.slot)
                 }
-                _ => item.name.clone(),
+                _ => item.stack_name(game),
             }
         })
         .collect()
@@ -617,7 +1860,7 @@ fn inventory_menu(inventory: &[Entity], header: &str) -> Option<usize> {
     let inventory_index = menu(header, &options);
 
     // if an item was chosen, return it
-    if inventory.len() > 0 {
+    if !inventory.is_empty() {
 	//pretty print
 	println!("Inv index: {:?}", inventory_index);
         inventory_index
@@ -630,9 +1873,19 @@ fn draw_bar(name: &str, total_width: i32, value: i32, max: i32) -> String {
     let mut s=String::from(name);
     let bar_width = (value as f32 / max as f32 * total_width as f32) as i32;
 
+    let mut bar = String::new();
     for _i in 0..bar_width+1{
-	s.push_str("\u{2588}")
+	bar.push(tiles::tileset().bar())
     }
+    // green while healthy, red when it's getting dangerous
+    let color = if value * 2 > max {
+        Color::BrightGreen
+    } else if value * 4 > max {
+        Color::BrightYellow
+    } else {
+        Color::BrightRed
+    };
+    s.push_str(&render::paint(&bar, color));
 
     if total_width > bar_width{
 	let diff = total_width - bar_width;
@@ -644,61 +1897,123 @@ fn draw_bar(name: &str, total_width: i32, value: i32, max: i32) -> String {
     s.push(' ');
     //deref
     s.push_str(&value.to_string());
-    s.push_str("\\");
+    s.push('\\');
     s.push_str(&max.to_string());
-    return s;
-}
-
-fn print_all(entities: &[Entity], map: &Map, seen: &HashSet<(i32, i32)>) {
-    let mut s=String::new();
-    
-    // go through all tiles, and print
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
-	    if seen.contains(&(x,y)) {
-                let stairs = map[x as usize][y as usize].stairs;
-                if stairs {
-                    s.push('>');
-                }
-            	let wall = map[x as usize][y as usize].block_sight;
-            	if wall {
-		            s.push('#');
-                } else {
-		            s.push('.');
-            	}
-	    }
-	    else {
-		s.push(' ');
-	    }
+    s
+}
+
+/// the map on the left, the sidebar on the right and this turn's messages underneath
+fn print_screen(entities: &[Entity], game: &Game, seen: &HashSet<(i32, i32)>) {
+    // screen reader users get the same view described in words instead (see narrate.rs)
+    if narrate::is_narrative() {
+        for line in narrate::describe(entities, game, seen) {
+            println!("{}", line);
+        }
+        println!();
+        for (kind, text) in messages::this_turn() {
+            messages::show(kind, &text);
+        }
+        return;
+    }
+    // draw the map into a buffer (see render.rs), then print it in one go
+    let buffer = render::render(entities, &game.map, seen);
+    let map_text = if render::color_enabled() { buffer.to_ansi() } else { buffer.to_text() };
+    let map_lines: Vec<&str> = map_text.lines().collect();
+    let sidebar = sidebar(entities, game, seen);
+    let blank = " ".repeat(buffer.width() as usize);
+
+    let mut screen = Vec::new();
+    for row in 0..cmp::max(map_lines.len(), sidebar.len()) {
+        let mut line = map_lines.get(row).copied().unwrap_or(&blank).to_string();
+        if let Some(side) = sidebar.get(row) {
+            line.push_str("  ");
+            line.push_str(side);
         }
-	//our row ended, add a line break
-	s.push('\n');
+        screen.push(line);
+    }
+    screen.push(String::new());
+    // all of it, or just what changed since last time (see render::present)
+    render::present(screen);
+    for (kind, text) in messages::this_turn() {
+        messages::show(kind, &text);
     }
+}
 
-    //println!("{}", s);
+/// how badly hurt something looks
+fn health_state(hp: i32, max_hp: i32) -> &'static str {
+    if hp >= max_hp {
+        "unhurt"
+    } else if hp * 2 > max_hp {
+        "lightly wounded"
+    } else if hp * 4 > max_hp {
+        "wounded"
+    } else {
+        "nearly dead"
+    }
+}
+
+/// what the player fights with
+fn weapon_name(player: &Entity, game: &Game) -> String {
+    // weapons go in the right hand (two-handed ones too), whatever they do for damage; the
+    // left hand is for shields
+    get_equipped_in_slot(Slot::RightHand, &player.inventory)
+        .map(|id| &player.inventory[id])
+        .filter(|item| item.item == Some(Item::Equipment))
+        .map_or_else(|| "bare hands".to_string(), |item| game.item_name(item))
+}
+
+/// the lines of the sidebar: the player's state, then the monsters in view
+fn sidebar(entities: &[Entity], game: &Game, seen: &HashSet<(i32, i32)>) -> Vec<String> {
+    let player = &entities[PLAYER];
+    let hp = player.fighter.map_or(0, |f| f.hp);
+    let mut lines = vec![
+        player.name.clone(),
+        draw_bar("HP: ", 4, hp, player.get_max_hp()),
+        format!("Depth: {}", game.dungeon_level),
+        format!("Turns: {}", game.turns),
+        format!("Weapon: {}", weapon_name(player, game)),
+    ];
+
+    let mut status = Vec::new();
+    if let Some(hunger) = player.hunger {
+        if hunger.state() != HungerState::NotHungry {
+            status.push(hunger.state().to_string());
+        }
+    }
+    if player.poisoned > 0 {
+        status.push(format!("Poisoned ({})", player.poisoned));
+    }
+    if player.slowed > 0 {
+        status.push("Slowed".to_string());
+    }
+    if !status.is_empty() {
+        lines.push(status.join(", "));
+    }
 
-    // draw all objects in the list
-    for object in entities {
-       s = object.draw(&mut s, seen);
-    }  
-    println!("{}", s);
+    let visible: Vec<&Entity> = entities
+        .iter()
+        .skip(PLAYER + 1)
+        .filter(|e| e.fighter.is_some() && seen.contains(&e.pos()))
+        .collect();
+    if !visible.is_empty() {
+        lines.push(String::new());
+        lines.push("In view:".to_string());
+        for monster in visible {
+            let fighter = monster.fighter.unwrap();
+            lines.push(format!("{} {} ({})", tiles::tileset().entity(monster), monster.name, health_state(fighter.hp, monster.get_max_hp())));
+        }
+    }
+    lines
 }
 
-fn prompt_and_handle_keys(game: &mut Game, entities: & mut Vec<Entity>) -> PlayerAction {
-       use std::io::{stdin,stdout};
+fn prompt_and_handle_keys(game: &mut Game, entities: & mut Vec<Entity>, seen: &HashSet<(i32, i32)>) -> PlayerAction {
        use PlayerAction::*;
 
-       let mut s=String::new();
-       print!("Please enter command: ");
-       let _=stdout().flush();
-       stdin().read_line(&mut s).expect("Did not enter a correct command");
-       if let Some('\n')=s.chars().next_back() {
-	   s.pop();
-       }
-       if let Some('\r')=s.chars().next_back() {
-	   s.pop();
-       }
-       println!("You typed: {}",s);
+       //end of input (e.g. piped commands ran out) means we're done
+       let s = match term::read_command("Please enter command: ") {
+           Some(s) => s,
+           None => return Exit,
+       };
 
        //key handling
        if s.trim() == "Q" {
@@ -706,52 +2021,179 @@ fn prompt_and_handle_keys(game: &mut Game, entities: & mut Vec<Entity>) -> Playe
            return Exit; //exit
        }
        if s.trim() == "w" {
-           move_by(0, -1, 0, entities, game);
+           move_by(PLAYER, -1, 0, entities, game);
 	        return TookTurn;
        }
        if s.trim() == "e" {
-           move_by(0, 1, 0, entities, game);
+           move_by(PLAYER, 1, 0, entities, game);
 	   return TookTurn;
        }
        if s.trim() == "n" {
-           move_by(0, 0, -1, entities, game);
+           move_by(PLAYER, 0, -1, entities, game);
 	   return TookTurn;
        }
        if s.trim() == "s" {
-           move_by(0, 0, 1,entities, game);
+           move_by(PLAYER, 0, 1,entities, game);
 	   return TookTurn;
        }
        if s.trim() == ">" || s.trim() == "<" {
            //tuple unpacking
-           let (x, y) = entities[0].pos();
-           if game.map[x as usize][y as usize].stairs {
-               //new level
-               next_level(entities, game);
-               return DidntTakeTurn;
+           let (x, y) = entities[PLAYER].pos();
+           let terrain = game.map[x as usize][y as usize].terrain;
+           if s.trim() == ">" && terrain == Terrain::DownStairs {
+               let depth = game.dungeon_level + 1;
+               change_level(entities, game, depth);
+           } else if s.trim() == "<" && terrain == Terrain::UpStairs {
+               let depth = game.dungeon_level - 1;
+               change_level(entities, game, depth);
+           } else {
+               message!("There are no stairs going that way here.");
+           }
+           return DidntTakeTurn;
+       }
+       if s.trim() == "o" || s.trim() == "c" {
+           // open or close an adjacent door
+           if let Some((dx, dy)) = prompt_direction() {
+               let (x, y) = entities[PLAYER].pos();
+               // nothing there to open or close doesn't cost a turn
+               let done = if s.trim() == "o" {
+                   open_door(PLAYER, x + dx, y + dy, entities, game)
+               } else {
+                   close_door(x + dx, y + dy, entities, game)
+               };
+               if done {
+                   return TookTurn;
+               }
+           }
+           return DidntTakeTurn;
+       }
+       if s.trim() == "S" {
+           // a careful search finds most things
+           if search(75, entities, game) == 0 {
+               message!("You search around but find nothing.");
+           }
+           return TookTurn;
+       }
+       if s.trim() == "l" || s.trim() == "look" || s.starts_with("l ") || s.starts_with("look ") {
+           // "look" on its own lists what's in view, "look <name or letter>" describes one thing
+           let sightings = look::in_view(entities, game, seen);
+           let mut query = s.trim().split_once(' ').map_or("", |(_, q)| q).to_string();
+           if query.is_empty() {
+               if sightings.is_empty() {
+                   println!("You see nothing of interest.");
+               }
+               for line in look::list(entities, &sightings) {
+                   println!("{}", line);
+               }
+               // no room on a single keypress for what to look at
+               if term::is_raw() && !sightings.is_empty() {
+                   query = prompt_line("Look at which (Enter for none)? ");
+               }
+           }
+           if !query.trim().is_empty() {
+               match look::find(&sightings, &query) {
+                   Some(sighting) => {
+                       for line in look::describe(&sighting.target, entities, game) {
+                           println!("{}", line);
+                       }
+                   }
+                   None => println!("You don't see any {} here.", query.trim()),
+               }
+           }
+           term::pause();
+           return DidntTakeTurn;
+       }
+       if s.trim() == "R" || s.trim() == "redraw" {
+           // send the whole screen again next time, in case the terminal got out of step
+           render::forget_frame();
+           return DidntTakeTurn;
+       }
+       if s.trim() == "m" || s.trim() == "overview" {
+           // the explored part of this level, shrunk to fit (see overview.rs)
+           for line in overview::level_map(entities, game) {
+               println!("{}", line);
            }
-           else
-           {
-               return DidntTakeTurn;
+           term::pause();
+           return DidntTakeTurn;
+       }
+       if s.trim() == "M" || s.trim() == "dungeon" {
+           for line in overview::dungeon(game) {
+               println!("{}", line);
+           }
+           term::pause();
+           return DidntTakeTurn;
+       }
+       if s.trim() == "D" {
+           if let Some((dx, dy)) = prompt_direction() {
+               let (x, y) = entities[PLAYER].pos();
+               if disarm_trap(x + dx, y + dy, entities, game) {
+                   return TookTurn;
+               }
            }
+           return DidntTakeTurn;
        }
        if s.trim() == "g" {
 	   // pick up an item
-    	   let item_id = entities
+    	   let item_ids: Vec<usize> = entities
            .iter()
-           .position(|e| e.pos() == entities[0].pos() && e.item.is_some());
+           .enumerate()
+           .filter(|(_, e)| e.pos() == entities[PLAYER].pos() && e.item.is_some())
+           .map(|(id, _)| id)
+           .collect();
+           let item_id = if item_ids.len() > 1 {
+               // several things lying here, ask which one; a menu only has letters for 26
+               let more = item_ids.len().saturating_sub(26);
+               let names: Vec<String> = item_ids.iter().take(26).map(|&id| entities[id].stack_name(game)).collect();
+               let header = if more > 0 {
+                   format!("Pick up which item? ({} more underneath)\n", more)
+               } else {
+                   "Pick up which item?\n".to_string()
+               };
+               menu(&header, &names).map(|index| item_ids[index])
+           } else {
+               item_ids.first().cloned()
+           };
            if let Some(item_id) = item_id {
-        	pick_item_up(item_id, entities, &mut game.inventory);
+        	pick_item_up(item_id, entities, game);
 		return TookTurn;
            }
-           //return DidntTakeTurn;
+           if item_ids.is_empty() {
+               message!("There is nothing here to pick up.");
+           }
+           return DidntTakeTurn;
        }
        if s.trim() == "i" {
     	   // show the inventory
            let inventory_index = inventory_menu(
-           &game.inventory,
-           "Press the key next to an item to use it, or any other to cancel.\n");
+           &entities[PLAYER].inventory,
+           "Press the key next to an item to use it, or any other to cancel.\n",
+           game);
+           if let Some(inventory_index) = inventory_index {
+           	use_item(inventory_index, entities, game);
+           }
+           return DidntTakeTurn;
+       }
+       if s.trim() == "d" {
+           // show the inventory; if an item is selected, drop it
+           let inventory_index = inventory_menu(
+           &entities[PLAYER].inventory,
+           "Press the key next to an item to drop it, or any other to cancel.\n",
+           game);
            if let Some(inventory_index) = inventory_index {
-           	use_item(inventory_index, &mut game.inventory, entities);
+               let quantity = entities[PLAYER].inventory[inventory_index].quantity;
+               let amount = if quantity > 1 {
+                   let answer = prompt_line(&format!("Drop how many (1-{}, Enter for all)? ", quantity));
+                   parse_amount(&answer, quantity)
+               } else {
+                   Some(1)
+               };
+               match amount {
+                   Some(amount) => {
+                       drop_item(inventory_index, amount, entities, game);
+                       return TookTurn;
+                   }
+                   None => println!("Never mind."),
+               }
            }
            return DidntTakeTurn;
        }
@@ -769,86 +2211,181 @@ enum PlayerAction {
 #[derive(Serialize, Deserialize)]
 struct Game {
     map: Map,
-    inventory: Vec<Entity>,
     dungeon_level: u32,
+    seed: u64, //every level is generated from this, see rng.rs
+    turns: u32,
+    casual: bool, //keep the save when the player dies
+    kills: BTreeMap<String, u32>, //monster name -> how many the player killed
+    xp: u32, //the toughness (max HP) of everything the player killed
+    levels: HashMap<u32, Level>, //the levels we're not on right now
+    appearances: HashMap<String, String>, //real item name -> what it looks like until identified
+    identified: HashSet<String>,
 }
 
-fn next_level(entities: &mut Vec<Entity>, game: &mut Game){
-    print!("You descend deeper in the dungeons...");
-    game.dungeon_level += 1;
-    //make the new level
-    game.map = make_map();
-    
-    // Player is the first element, remove everything else
-    entities.truncate(1);
+/// A level we've left, kept as it was so we can come back to it
+#[derive(Serialize, Deserialize)]
+struct Level {
+    map: Map,
+    entities: Vec<Entity>, //everything except the player
+}
 
-    //create NPCs
-    let x = rand::thread_rng().gen_range(1,18);
-    let y = rand::thread_rng().gen_range(1,18);
-    let mut npc = Entity::new(x,y, 'k', "kobold");
-    npc.fighter = Some(Fighter {
-                    max_hp: 10,
-                    hp: 10,
-                    defense: 0,
-                    base_damage: 4,
-		    on_death: DeathCallback::Monster,
-                });
-    npc.ai = Some(Ai::Normal);
-    let x = rand::thread_rng().gen_range(1,18);
-    let y = rand::thread_rng().gen_range(1,18);
-    let mut npc2 = Entity::new(x,y, 'k', "kobold");
-    npc2.fighter = Some(Fighter {
-                    max_hp: 10,
-                    hp: 10,
-                    defense: 0,
-                    base_damage: 4,
-		    on_death: DeathCallback::Monster,
-                });
-    npc2.ai = Some(Ai::Normal);
+impl Game {
+    pub fn new() -> Self {
+        let seed = rng::random_seed();
+        rng::reseed(rng::level_seed(seed, 1));
+        Game {
+            map: make_map(1),
+            dungeon_level: 1,
+            seed,
+            turns: 0,
+            casual: false,
+            kills: BTreeMap::new(),
+            xp: 0,
+            levels: HashMap::new(),
+            appearances: random_appearances(),
+            identified: HashSet::new(),
+        }
+    }
 
+    /// the name the player knows the item by
+    pub fn item_name(&self, item: &Entity) -> String {
+        if self.is_identified(&item.name) {
+            item.name.clone()
+        } else {
+            self.appearances[&item.name].clone()
+        }
+    }
 
-}
+    /// the deepest dungeon level visited, whether or not we're still down there
+    pub fn max_depth(&self) -> u32 {
+        self.levels.keys().copied().chain(Some(self.dungeon_level)).max().unwrap_or(1)
+    }
 
+    pub fn is_identified(&self, name: &str) -> bool {
+        !self.appearances.contains_key(name) || self.identified.contains(name)
+    }
 
-fn main_menu() -> Option<(Vec<Entity>, Game)>{
-    use std::io::{stdin,stdout};
+    pub fn identify(&mut self, name: &str) {
+        self.identified.insert(name.to_string());
+    }
+}
 
-    let mut s=String::new();
+/// shuffle the potion looks and make up scroll labels, so every game is different
+fn random_appearances() -> HashMap<String, String> {
+    let mut appearances = HashMap::new();
 
-    print!("1) New game");
-    print!("2) Load game");
+    let mut looks = POTION_LOOKS.to_vec();
+    rng::shuffle(&mut looks);
+    for (name, look) in POTION_NAMES.iter().zip(looks) {
+        appearances.insert(name.to_string(), format!("{} potion", look));
+    }
 
-    print!("Please enter command: ");
-    let _=stdout().flush();
-    stdin().read_line(&mut s).expect("Did not enter a correct command");
-    if let Some('\n')=s.chars().next_back() {
-	   s.pop();
+    for name in SCROLL_NAMES {
+        // two kinds of scroll with the same label would give the game away
+        let label = loop {
+            let label: String = (0..rng::gen_range(2, 4))
+                .map(|_| SCROLL_SYLLABLES[rng::gen_range(0, SCROLL_SYLLABLES.len())])
+                .collect();
+            let label = format!("scroll labelled {}", label.to_uppercase());
+            if !appearances.values().any(|taken| *taken == label) {
+                break label;
+            }
+        };
+        appearances.insert(name.to_string(), label);
     }
-    if let Some('\r')=s.chars().next_back() {
-	   s.pop();
+    appearances
+}
+
+/// leave the current level as it is and go to another one, making it if we haven't been there yet;
+/// the player ends up on the stairs leading back where they came from
+fn change_level(entities: &mut Vec<Entity>, game: &mut Game, depth: u32) {
+    let going_down = depth > game.dungeon_level;
+    if going_down {
+        message!("You descend deeper in the dungeons...");
+    } else {
+        message!("You climb back up...");
     }
-    println!("You typed: {}",s);
 
-    //key handling
-    if s.trim() == "1" {
-        print!("New game!");
-        let (mut entities, mut game) = new_game();
-        return Some((entities, game));
+    // Player is the first element, everything else stays behind
+    let left_behind = Level {
+        map: std::mem::take(&mut game.map),
+        entities: entities.split_off(PLAYER + 1),
+    };
+    game.levels.insert(game.dungeon_level, left_behind);
+    game.dungeon_level = depth;
+
+    let arrival = if going_down { Terrain::UpStairs } else { Terrain::DownStairs };
+    match game.levels.remove(&depth) {
+        Some(level) => {
+            // a level we've been to stays as we left it, even if we cleared it out
+            game.map = level.map;
+            entities.extend(level.entities);
+            let (x, y) = find_terrain(&game.map, arrival).unwrap_or_else(|| random_floor(&game.map));
+            entities[PLAYER].set_pos(x, y);
+        }
+        None => {
+            //make the new level, the same one every time for this seed
+            rng::reseed(rng::level_seed(game.seed, depth));
+            game.map = make_map(depth);
+            let (x, y) = find_terrain(&game.map, arrival).unwrap_or_else(|| random_floor(&game.map));
+            entities[PLAYER].set_pos(x, y);
+            populate_level(entities, game);
+        }
     }
-    if s.trim() == "2" {
-        print!("Load game!");
-        //load game
-        let (mut entities, mut game) = load_game().unwrap();
-        return Some((entities, game));
+}
+
+fn populate_level(entities: &mut Vec<Entity>, game: &Game) {
+    // kobolds get a bit nastier deeper down
+    let damage = if game.dungeon_level > 1 { 4 } else { 3 };
+
+    //create NPCs
+    let (x, y) = random_floor(&game.map);
+    entities.push(make_kobold(x, y, damage));
+    let (x, y) = random_floor(&game.map);
+    entities.push(make_kobold(x, y, damage));
+
+    // something to eat, so that going deeper is worth it
+    let (x, y) = random_floor(&game.map);
+    entities.push(make_ration(x, y));
+
+    // and something to find out about
+    let (x, y) = random_floor(&game.map);
+    entities.push(make_potion(x, y));
+    let (x, y) = random_floor(&game.map);
+    entities.push(make_scroll(x, y));
+
+    if let Some(key) = make_key(&game.map, entities[PLAYER].pos()) {
+        entities.push(key);
     }
-    //default
-    None
 }
 
+/// one of the kinds of potion, at random
+fn make_potion(x: i32, y: i32) -> Entity {
+    let (name, item, description) = match rng::gen_range(0, 3) {
+        0 => ("potion of poison", Item::Poison, "A sickly green brew. Don't drink it."),
+        _ => ("healing potion", Item::Heal, "A small flask of red liquid that closes wounds."),
+    };
+    let mut potion = Entity::new(x, y, '!', name);
+    potion.item = Some(item);
+    potion.description = Some(description.into());
+    potion
+}
 
-fn new_game() -> (Vec<Entity>, Game) {
-    //create player
-    let mut player = Entity::new(1,1, '@', "Player");
+/// one of the kinds of scroll, at random
+fn make_scroll(x: i32, y: i32) -> Entity {
+    let (name, item, description) = match rng::gen_range(0, 2) {
+        0 => ("scroll of magic mapping", Item::Mapping, "Reading it shows you the layout of the level."),
+        _ => ("scroll of identify", Item::Identify, "Reading it reveals what an unknown item is."),
+    };
+    let mut scroll = Entity::new(x, y, '?', name);
+    scroll.item = Some(item);
+    scroll.description = Some(description.into());
+    scroll
+}
+
+/// a new character, as they start the game
+fn make_player(x: i32, y: i32, name: &str) -> Entity {
+    let mut player = Entity::new(x, y, '@', name);
     player.fighter = Some(Fighter {
         max_hp: 30,
         hp: 30,
@@ -856,144 +2393,393 @@ fn new_game() -> (Vec<Entity>, Game) {
         base_damage: 5,
 	on_death: DeathCallback::Player,
     });
+    player.hunger = Some(Hunger { nutrition: START_NUTRITION });
+    player
+}
 
-    //create NPCs
-    let x = rand::thread_rng().gen_range(1,18);
-    let y = rand::thread_rng().gen_range(1,18);
+/// a player with small round numbers for stats and no hunger to worry about, for the tests
+#[cfg(test)]
+fn test_player(x: i32, y: i32) -> Entity {
+    let mut player = make_player(x, y, "Player");
+    player.fighter = Some(Fighter { max_hp: 10, hp: 10, defense: 0, base_damage: 1, on_death: DeathCallback::Player });
+    player.hunger = None;
+    player
+}
+
+/// every tile of the map the same, for the tests to build on
+#[cfg(test)]
+fn fill_map(map: &mut Map, tile: Tile) {
+    for spot in map.iter_mut().flatten() {
+        *spot = tile;
+    }
+}
+
+fn make_ration(x: i32, y: i32) -> Entity {
+    let mut ration = Entity::new(x, y, '%', "food ration");
+    ration.item = Some(Item::Food(800));
+    ration.description = Some("Dried meat and hard bread. Not tasty, but filling.".into());
+    ration
+}
+
+/// a kobold, which might have picked up some gear it will fight with
+fn make_kobold(x: i32, y: i32, base_damage: i32) -> Entity {
     let mut npc = Entity::new(x,y, 'k', "kobold");
+    npc.description = Some("A small, scaly dog-like humanoid. Cowardly alone, dangerous in packs.".into());
     npc.fighter = Some(Fighter {
                     max_hp: 10,
                     hp: 10,
                     defense: 0,
-                    base_damage: 3,
+                    base_damage,
 		    on_death: DeathCallback::Monster,
                 });
     npc.ai = Some(Ai::Normal);
-    let x = rand::thread_rng().gen_range(1,18);
-    let y = rand::thread_rng().gen_range(1,18);
-    let mut npc2 = Entity::new(x,y, 'k', "kobold");
-    npc2.fighter = Some(Fighter {
-                    max_hp: 10,
-                    hp: 10,
-                    defense: 0,
-                    base_damage: 3,
-		    on_death: DeathCallback::Monster,
-                });
-    npc2.ai = Some(Ai::Normal);
+
+    if rng::gen_range(0, 2) == 0 {
+        let mut dagger = Entity::new(x, y, '/', "dagger");
+        dagger.item = Some(Item::Equipment);
+        dagger.description = Some("A short, rusty blade.".into());
+        dagger.equipment = Some(Equipment{equipped: true, damage_bonus: 1, ..Equipment::new(Slot::RightHand)});
+        npc.inventory.push(dagger);
+    }
+    if rng::gen_range(0, 3) == 0 {
+        let mut helmet = Entity::new(x, y, '[', "leather cap");
+        helmet.item = Some(Item::Equipment);
+        helmet.description = Some("A cap of boiled leather, sized for a kobold's head.".into());
+        helmet.equipment = Some(Equipment{equipped: true, defense_bonus: 1, ..Equipment::new(Slot::Head)});
+        npc.inventory.push(helmet);
+    }
+    npc
+}
+
+
+/// returns the game to play and the save slot it goes in, or None to quit
+fn main_menu() -> Option<(Vec<Entity>, Game, String)>{
+    loop {
+        println!("1) New game");
+        println!("2) Load game");
+        println!("3) High scores");
+        println!("4) Quit");
+
+        let s = term::read_command("Please enter command: ")?;
+
+        //key handling
+        match s.trim() {
+            "1" => {
+                println!("New game!");
+                let (entities, game) = new_game();
+                let slot = save::free_slot(&entities[PLAYER].name);
+                return Some((entities, game, slot));
+            }
+            "2" => {
+                if let Some(loaded) = load_menu() {
+                    return Some(loaded);
+                }
+            }
+            "3" => match scores::load() {
+                Ok(scores) => println!("{}", scores::table(&scores, None)),
+                Err(e) => println!("Could not read the high scores: {}", e),
+            },
+            "4" | "Q" => return None,
+            _ => println!("That's not one of the choices."),
+        }
+    }
+}
+
+/// pick a saved game; any problem is reported and we go back to the main menu
+fn load_menu() -> Option<(Vec<Entity>, Game, String)> {
+    let slots = save::list_slots();
+    if slots.is_empty() {
+        println!("There are no saved games.");
+        return None;
+    }
+    let options: Vec<String> = slots
+        .iter()
+        .map(|(slot, summary)| match summary {
+            Ok(summary) => format!("{}: {}, dungeon level {}, {} turns", slot, summary.name, summary.depth, summary.turns),
+            Err(e) => format!("{}: can't be loaded, {}", slot, e),
+        })
+        .collect();
+    let index = menu("Load which game?", &options)?;
+    let slot = &slots[index].0;
+    match save::load(slot) {
+        Ok((entities, game)) => {
+            println!("Load game!");
+            Some((entities, game, slot.clone()))
+        }
+        Err(e) => {
+            println!("Could not load {}: {}", slot, e);
+            None
+        }
+    }
+}
+
+
+fn new_game() -> (Vec<Entity>, Game) {
+    //create player
+    let name = prompt_line("What is your name? ");
+    let name = if name.trim().is_empty() { "Player" } else { name.trim() };
+    let player = make_player(1, 1, name);
+
+    let mut game = Game::new();
+    game.casual = prompt_line("Casual mode, keeping your save when you die (y/n)? ").trim() == "y";
+
+    //create NPCs
+    let (x, y) = random_floor(&game.map);
+    let npc = make_kobold(x, y, 3);
+    let (x, y) = random_floor(&game.map);
+    let npc2 = make_kobold(x, y, 3);
+    let potion_description = "A small flask of red liquid that closes wounds.";
     let mut object = Entity::new(2, 5, '!', "healing potion");
     object.item = Some(Item::Heal);
+    object.quantity = 2;
+    object.description = Some(potion_description.into());
+    let mut object2 = Entity::new(3, 5, '!', "healing potion");
+    object2.item = Some(Item::Heal);
+    object2.description = Some(potion_description.into());
 
     // create a sword
     let mut sword = Entity::new(2, 2, '/', "sword");
     sword.item = Some(Item::Equipment);
-    sword.equipment = Some(Equipment{equipped: false, slot: Slot::RightHand, damage_bonus:1});
+    sword.description = Some("A plain but well balanced arming sword.".into());
+    sword.equipment = Some(Equipment{damage_bonus: 1, ..Equipment::new(Slot::RightHand)});
+
+    // a greatsword hits harder, but needs both hands
+    let mut greatsword = Entity::new(3, 2, '/', "greatsword");
+    greatsword.item = Some(Item::Equipment);
+    greatsword.description = Some("A huge two-handed blade. Slow, but it hits hard.".into());
+    greatsword.equipment = Some(Equipment{two_handed: true, damage_bonus: 3, ..Equipment::new(Slot::RightHand)});
+
+    // and some armour
+    let mut shield = Entity::new(4, 2, '[', "shield");
+    shield.item = Some(Item::Equipment);
+    shield.description = Some("A round wooden shield with an iron rim.".into());
+    shield.equipment = Some(Equipment{defense_bonus: 1, ..Equipment::new(Slot::LeftHand)});
+    let mut armour = Entity::new(2, 3, '[', "leather armour");
+    armour.item = Some(Item::Equipment);
+    armour.description = Some("A jerkin of hardened leather.".into());
+    armour.equipment = Some(Equipment{defense_bonus: 1, ..Equipment::new(Slot::Body)});
+    let mut ring = Entity::new(3, 3, '=', "ring of vitality");
+    ring.item = Some(Item::Equipment);
+    ring.description = Some("A warm gold band. Wearing it makes you feel hardier.".into());
+    ring.equipment = Some(Equipment{max_hp_bonus: 5, ..Equipment::new(Slot::LeftRing)});
+
+    let mut scroll = Entity::new(4, 5, '?', "scroll of identify");
+    scroll.item = Some(Item::Identify);
+    scroll.description = Some("Reading it reveals what an unknown item is.".into());
+    let ration = make_ration(5, 5);
+
+    let mut entities = vec![player, npc, npc2, object, object2, scroll, ration, sword, greatsword, shield, armour, ring];
+    if let Some(key) = make_key(&game.map, entities[PLAYER].pos()) {
+        entities.push(key);
+    }
 
-    let mut entities = vec![player, npc, npc2, object, sword];
+    (entities, game)
+}
 
-    let mut game = Game { 
-        map: make_map(),
-        inventory: vec![],
-        dungeon_level: 1,
-        };
+/// the tiles the player can see from where they stand
+fn player_fov(entities: &[Entity], map: &Map) -> HashSet<(i32, i32)> {
+    let mut seen = HashSet::new();
+    //call function from other file
+    ppfov::ppfov(
+        (entities[PLAYER].x, entities[PLAYER].y),
+        5,
+        |x, y| if x > 0 && x < MAP_WIDTH && y > 0 && y < MAP_HEIGHT { map[x as usize][y as usize].block_sight() } else { true },
+        |x, y| {
+            seen.insert((x, y));
+        },
+    );
+    seen
+}
 
-    (entities, game)
+fn update_fov(entities: &[Entity], game: &mut Game, seen_set: &mut HashSet<(i32, i32)>) {
+    *seen_set = player_fov(entities, &game.map);
+    // remember what we've seen
+    for &(x, y) in seen_set.iter() {
+        if (0..MAP_WIDTH).contains(&x) && (0..MAP_HEIGHT).contains(&y) {
+            game.map[x as usize][y as usize].explored = true;
+        }
+    }
 }
 
-fn play_game(entities: &mut Vec<Entity>, game: &mut Game, seen_set: &mut HashSet<(i32, i32)>, game_quit: bool) {
-    while ! game_quit {
+fn play_game(entities: &mut Vec<Entity>, game: &mut Game, seen_set: &mut HashSet<(i32, i32)>, slot: &str) {
+    messages::clear();
+    render::forget_frame();
+    loop {
+       // in raw mode we redraw in place instead of scrolling
+       // (unless we're only sending what changed, then present takes care of it)
+       if term::is_raw() && !render::diff_enabled() {
+           term::clear_screen();
+       }
        //the order is important, we can't prompt first and draw second because that results in 
 	//borrowing twice for some reason
-       //render the map
-       print_all(&entities, &game.map, &seen_set);
-	    // draw basic infos
-        let hp = entities[0].fighter.map_or(0, |f| f.hp);
-        let max_hp = entities[0].fighter.map_or(0, |f| f.max_hp);
-	    println!("{}", draw_bar("HP: ", 4, hp, max_hp));
-        println!("Dungeon level: {}", game.dungeon_level);
+       //render the map, the sidebar and what just happened
+       print_screen(entities, game, seen_set);
+        messages::new_turn();
        //super unintuitive but avoids use of moved variable error
        //let player = &mut entities[0];
 	
-       let player_action = prompt_and_handle_keys(game, entities);
+       let level_before = game.dungeon_level;
+       let player_action = prompt_and_handle_keys(game, entities, seen_set);
        //println!("player x {:?}", player.x);
        //println!("player y {:?}", player.y);
        //println!("\u{2588}");
 
-        //fov
-	    //clear set
-	    seen_set.clear();
-	    //call function from other file
-	    ppfov::ppfov(
-      	(entities[0].x, entities[0].y),
-      	5,
-      	|x, y| if x > 0 && x < 20 && y > 0 && y < 20 { game.map[x as usize][y as usize].block_sight } else { true },
-      	|x, y| {
-        	seen_set.insert((x, y));
-      	   },
-    	);
+        //fov (doors might have opened or closed, too)
+        update_fov(entities, game, seen_set);
+        // whatever was shown instead of taking a turn (inventory, look...) may have scrolled the screen
+        if player_action == PlayerAction::DidntTakeTurn {
+            render::forget_frame();
+        }
 	
 	    //println!("{:?}", seen_set);
 	    if player_action == PlayerAction::Exit {
             //save game when quitting
-            save_game(entities, game);
-            break;
+            match save::save(slot, entities, game) {
+                Ok(()) => println!("Saved as {}.", slot),
+                Err(e) => println!("Could not save the game: {}", e),
+            }
+            println!("You quit!");
+            return;
         }
 
-        // let monsters take their turn
-        if entities[0].alive && player_action != PlayerAction::DidntTakeTurn {
-            for id in 0..entities.len() {
-                if entities[id].ai.is_some() {
-                    //println!("Taking turn...");
-                    ai_take_turn(id, entities, &seen_set, game);
+        // let monsters take their turn (more of them if we fainted or got slowed down)
+        if entities[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
+            game.turns += 1;
+            let mut rounds = 1 + entities[PLAYER].slowed;
+            entities[PLAYER].slowed = 0;
+            if tick_hunger(entities) {
+                rounds += 1;
+            }
+            tick_poison(PLAYER, entities);
+            // keep an eye out for traps as we go
+            search(10, entities, game);
+            for _ in 0..rounds {
+                for id in 0..entities.len() {
+                    if entities[id].ai.is_some() {
+                        //println!("Taking turn...");
+                        ai_take_turn(id, entities, seen_set, game);
+                    }
                 }
             }
         }
+
+        if !entities[PLAYER].alive {
+            break;
+        }
+        if game.dungeon_level != level_before || (player_action == PlayerAction::TookTurn && game.turns % AUTOSAVE_TURNS == 0) {
+            autosave(slot, entities, game);
+        }
     }
 
-    println!("You quit!");
+    // only way out of the loop without quitting
+    game_over(entities, game, slot);
 }
 
-//save/load
-fn save_game(entities: &[Entity], game: &Game) -> Result<(), Box<Error>> {
-    let save_data = serde_json::to_string(&(entities, game))?;
-    let mut file = File::create("savegame")?;
-    file.write_all(save_data.as_bytes())?;
-    Ok(())
+fn autosave(slot: &str, entities: &[Entity], game: &Game) {
+    if let Err(e) = save::save(slot, entities, game) {
+        println!("Autosave failed: {}", e);
+    }
 }
 
-fn load_game() -> Result<(Vec<Entity>, Game), Box<Error>> {
-    let mut json_save_state = String::new();
-    let mut file = File::open("savegame")?;
-    file.read_to_string(&mut json_save_state)?;
-    let result = serde_json::from_str::<(Vec<Entity>, Game)>(&json_save_state)?;
-    Ok(result)
+fn game_over(entities: &[Entity], game: &Game, slot: &str) {
+    // there's no next screen to show how it happened on
+    for (kind, text) in messages::this_turn() {
+        messages::show(kind, &text);
+    }
+    println!("*** GAME OVER ***");
+    println!("{}", morgue::morgue_text(entities, game));
+    match morgue::write(slot, entities, game) {
+        Ok(path) => println!("Morgue file written to {}.", path.display()),
+        Err(e) => println!("Could not write the morgue file: {}", e),
+    }
+    if game.casual {
+        // a casual run can die over and over from the same save, so it doesn't get a score
+        println!("Casual mode: your last save is kept, load it to try again. Scores are only kept for permadeath games.");
+        return;
+    }
+    match scores::record(scores::Entry::new(entities, game, false)) {
+        Ok((scores, rank)) => println!("{}", scores::table(&scores, rank)),
+        Err(e) => println!("Could not record your score: {}", e),
+    }
+    // permadeath: no going back to an earlier save
+    match save::delete(slot) {
+        Ok(()) => println!("Your save has been deleted."),
+        Err(e) => println!("Could not delete your save: {}", e),
+    }
 }
 
+//save/load
 fn main() {
-    let mut game_quit: bool = false;
-
-    let data = main_menu();
-    match data {
-        None => {
-            //quit because we went wrong
-            game_quit = true;
-        }
-        Some(data) => {
-            //unpack tuple
-            let (mut entities, mut game) = data;
-            let mut seen_set = HashSet::new();
-            //init fov
-            ppfov::ppfov(
-	        (2,2),
-	        5,
-	        |x, y| if x > 0 && x < 20 && y > 0 && y < 20 {game.map[x as usize][y as usize].block_sight } else { true },
-      	    |x, y| {
-        	    seen_set.insert((x, y));
-      	    },
-    	    );
-
-            play_game(&mut entities, &mut game, &mut seen_set, game_quit);
+    // colour is up to the terminal unless asked for on the command line
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let color = if args.iter().any(|a| a == "--no-color") {
+        false
+    } else {
+        args.iter().any(|a| a == "--color") || render::detect_color()
+    };
+    render::set_color(color);
+    // --view WxH sets how much of the map is on screen at once
+    if let Some(size) = args.iter().position(|a| a == "--view").and_then(|i| args.get(i + 1)) {
+        let mut parts = size.split('x').map(|n| n.parse::<i32>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(width)), Some(Ok(height))) => render::set_view_size(width, height),
+            _ => println!("Could not understand view size {}, it should look like 40x20.", size),
+        }
+    }
+    // --narrate describes the map in words instead of drawing it
+    narrate::set_narrative(args.iter().any(|a| a == "--narrate"));
+    // --diff sends only the lines that changed each turn, for slow connections; prose isn't drawn in lines
+    render::set_diff(args.iter().any(|a| a == "--diff") && !narrate::is_narrative());
+    // --tiles picks the tileset and remembers it for next time, otherwise it's whatever was picked last
+    let mut config = config::load().unwrap_or_else(|e| {
+        println!("Could not read the settings, using the defaults: {}", e);
+        config::Config::default()
+    });
+    if let Some(name) = args.iter().position(|a| a == "--tiles").and_then(|i| args.get(i + 1)) {
+        match tiles::Tileset::from_name(name) {
+            Some(tileset) => {
+                config.tileset = tileset;
+                if let Err(e) = config::save(&config) {
+                    println!("Could not save the settings: {}", e);
+                }
+            }
+            None => println!("There is no {} tileset, try ascii, unicode or cp437.", name),
         }
     }
+    tiles::set_tileset(config.tileset);
+    // keypress mode if asked for and we're on a terminal that can do it; the guard puts the terminal back
+    let _raw_mode = if args.iter().any(|a| a == "--raw") {
+        let raw_mode = term::enter_raw();
+        if raw_mode.is_none() {
+            println!("No terminal for --raw, using line mode.");
+        }
+        raw_mode
+    } else {
+        None
+    };
+
+    match save::rename_old_slots() {
+        Ok(0) => {}
+        Ok(n) => println!("Updated {} saved game(s) from an older version.", n),
+        Err(e) => println!("Could not update the saved games from an older version: {}", e),
+    }
+    // a save from the original game, from before there were slots
+    match save::import_legacy() {
+        Ok(Some(slot)) => println!("Found a saved game from an older version and imported it as {}.", slot),
+        Ok(None) => {}
+        Err(e) => println!("Found a saved game from an older version, but could not import it: {}", e),
+    }
+
+    // back to the menu after every game, until the player picks quit
+    while let Some(data) = main_menu() {
+        //unpack tuple
+        let (mut entities, mut game, slot) = data;
+        let mut seen_set = HashSet::new();
+        //init fov
+        update_fov(&entities, &mut game, &mut seen_set);
+
+        play_game(&mut entities, &mut game, &mut seen_set, &slot);
+    }
 
     //unpack a tuple
     //let (mut entities, mut game) = new_game();
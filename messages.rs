@@ -0,0 +1,106 @@
+//! The message log: the most recent game messages are remembered, so each turn's messages
+//! can be shown under the map and older ones again later (e.g. in the morgue file).
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use crate::render::{self, Color};
+
+// how many messages we hold on to
+const LOG_SIZE: usize = 100;
+
+/// like println!, but the line goes into the message log, to be shown with the next screen;
+/// put a Kind first to pick its colour, as in message!(Combat; "...")
+macro_rules! message {
+    ($kind:ident; $($arg:tt)*) => { crate::messages::add(crate::messages::Kind::$kind, format!($($arg)*)) };
+    ($($arg:tt)*) => { crate::messages::add(crate::messages::Kind::Info, format!($($arg)*)) };
+}
+
+/// what sort of news a message is, which decides its colour
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Info,
+    Combat,
+    Danger, //something bad happening to the player
+    Good,
+}
+
+impl Kind {
+    fn color(self) -> Option<Color> {
+        match self {
+            Kind::Info => None,
+            Kind::Combat => Some(Color::BrightYellow),
+            Kind::Danger => Some(Color::BrightRed),
+            Kind::Good => Some(Color::BrightGreen),
+        }
+    }
+}
+
+thread_local! {
+    static LOG: RefCell<VecDeque<(Kind, String)>> = const { RefCell::new(VecDeque::new()) };
+    // how many of the newest messages in LOG came since the turn started
+    static THIS_TURN: RefCell<usize> = const { RefCell::new(0) };
+}
+
+pub fn show(kind: Kind, text: &str) {
+    match kind.color() {
+        Some(color) => println!("{}", render::paint(text, color)),
+        None => println!("{}", text),
+    }
+}
+
+pub fn add(kind: Kind, text: String) {
+    LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        if log.len() == LOG_SIZE {
+            log.pop_front();
+        }
+        log.push_back((kind, text));
+    });
+    THIS_TURN.with(|count| *count.borrow_mut() += 1);
+}
+
+/// the messages since new_turn was last called
+pub fn this_turn() -> Vec<(Kind, String)> {
+    let count = THIS_TURN.with(|count| *count.borrow());
+    LOG.with(|log| {
+        let log = log.borrow();
+        log.iter().skip(log.len().saturating_sub(count)).cloned().collect()
+    })
+}
+
+pub fn new_turn() {
+    THIS_TURN.with(|count| *count.borrow_mut() = 0);
+}
+
+/// up to `count` of the latest messages, oldest first
+pub fn recent(count: usize) -> Vec<String> {
+    LOG.with(|log| {
+        let log = log.borrow();
+        log.iter().skip(log.len().saturating_sub(count)).map(|(_, text)| text.clone()).collect()
+    })
+}
+
+/// forget everything, for when a new game starts
+pub fn clear() {
+    LOG.with(|log| log.borrow_mut().clear());
+    new_turn();
+}
+
+#[test]
+fn log_tests() {
+    clear();
+    for i in 0..LOG_SIZE + 5 {
+        message!("message {}", i);
+    }
+    assert_eq!(recent(2), vec![format!("message {}", LOG_SIZE + 3), format!("message {}", LOG_SIZE + 4)]);
+    assert_eq!(recent(1000).len(), LOG_SIZE);
+    assert_eq!(recent(1000)[0], "message 5");
+    message!(Danger; "You feel {}.", "sick");
+    assert_eq!(LOG.with(|log| log.borrow().back().cloned()), Some((Kind::Danger, "You feel sick.".to_string())));
+    new_turn();
+    assert!(this_turn().is_empty());
+    message!("one");
+    message!("two");
+    assert_eq!(this_turn(), vec![(Kind::Info, "one".to_string()), (Kind::Info, "two".to_string())]);
+}
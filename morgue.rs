@@ -0,0 +1,104 @@
+//! Morgue files: a plain text account of how a character lived and died,
+//! written when the player dies.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{messages, Entity, Game, PLAYER};
+
+pub const MORGUE_DIR: &str = "morgue";
+
+// how much of the message log goes in
+const LAST_MESSAGES: usize = 20;
+
+/// the whole morgue file as text
+pub fn morgue_text(entities: &[Entity], game: &Game) -> String {
+    let player = &entities[PLAYER];
+    let cause = player.killed_by.as_deref().unwrap_or("something unknown");
+    // writing to a String can't fail
+    let mut text = String::new();
+    let _ = writeln!(
+        text,
+        "{} was killed by {} on dungeon level {} after {} turns.",
+        player.name, cause, game.dungeon_level, game.turns
+    );
+    let _ = writeln!(text, "Deepest level reached: {}", game.max_depth());
+
+    let _ = writeln!(text, "\nFinal stats");
+    let hp = player.fighter.map_or(0, |f| f.hp);
+    let _ = writeln!(
+        text,
+        "  HP: {}/{}  Defense: {}  Damage: {}",
+        hp.max(0),
+        player.get_max_hp(),
+        player.get_defense(),
+        player.get_damage()
+    );
+    if let Some(hunger) = player.hunger {
+        let _ = writeln!(text, "  Hunger: {}", hunger.state().to_string().to_lowercase());
+    }
+
+    let _ = writeln!(text, "\nEquipment");
+    let equipped: Vec<&Entity> = player.inventory.iter().filter(|item| item.equipment.is_some_and(|e| e.equipped)).collect();
+    if equipped.is_empty() {
+        let _ = writeln!(text, "  (nothing)");
+    }
+    for item in equipped {
+        let _ = writeln!(text, "  {}: {}", item.equipment.unwrap().slot, game.item_name(item));
+    }
+
+    let _ = writeln!(text, "\nInventory");
+    if player.inventory.is_empty() {
+        let _ = writeln!(text, "  (nothing)");
+    }
+    for item in &player.inventory {
+        let _ = writeln!(text, "  {}", item.stack_name(game));
+    }
+
+    let _ = writeln!(text, "\nKills");
+    if game.kills.is_empty() {
+        let _ = writeln!(text, "  (none)");
+    }
+    for (name, count) in &game.kills {
+        let _ = writeln!(text, "  {} {}", count, name);
+    }
+
+    let _ = writeln!(text, "\nLast messages");
+    for line in messages::recent(LAST_MESSAGES) {
+        let _ = writeln!(text, "  {}", line);
+    }
+    text
+}
+
+/// write the morgue file for this game, returning where it went
+pub fn write(slot: &str, entities: &[Entity], game: &Game) -> io::Result<PathBuf> {
+    fs::create_dir_all(MORGUE_DIR)?;
+    let path = PathBuf::from(MORGUE_DIR).join(format!("{}-{}.txt", slot, game.turns));
+    fs::write(&path, morgue_text(entities, game))?;
+    Ok(path)
+}
+
+#[test]
+fn morgue_tests() {
+    use crate::{make_kobold, test_player, Level};
+
+    let mut player = test_player(1, 1);
+    player.name = "Bob".into();
+    player.fighter.as_mut().unwrap().hp = 3;
+    let mut kobold = make_kobold(2, 1, 50);
+    kobold.name = "kobold".into();
+    let mut game = Game::new();
+    game.turns = 42;
+    // died after climbing back up from level 3
+    game.levels.insert(3, Level { map: Vec::new(), entities: Vec::new() });
+    game.kills.insert("kobold".into(), 2);
+    kobold.attack(&mut player);
+    assert!(!player.alive);
+
+    let text = morgue_text(&[player], &game);
+    assert!(text.starts_with("Bob was killed by a kobold on dungeon level 1 after 42 turns."));
+    assert!(text.contains("Deepest level reached: 3"));
+    assert!(text.contains("  2 kobold"));
+}
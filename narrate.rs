@@ -0,0 +1,165 @@
+//! Narrative mode: instead of drawing the map, describe it in words for players using a
+//! screen reader. It is built from the same field of view and entities as the map.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::look::{self, Sighting, Target};
+use crate::{health_state, weapon_name, Entity, Game, HungerState, Terrain, PLAYER};
+
+static NARRATIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_narrative(on: bool) {
+    NARRATIVE.store(on, Ordering::Relaxed);
+}
+
+pub fn is_narrative() -> bool {
+    NARRATIVE.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    // what was in view last time we described it, and on which level
+    static LAST_SEEN: RefCell<Option<(u32, Vec<Sighting>)>> = const { RefCell::new(None) };
+}
+
+/// "kobold, 3 north 2 east"
+fn placed(sighting: &Sighting, px: i32, py: i32) -> String {
+    format!("{}, {}", sighting.name, look::direction(sighting.x - px, sighting.y - py))
+}
+
+/// the rough size and shape of the open space the player can see
+fn surroundings(game: &Game, seen: &HashSet<(i32, i32)>) -> String {
+    let open: Vec<&(i32, i32)> = seen
+        .iter()
+        .filter(|&&(x, y)| game.map.get(x as usize).and_then(|c| c.get(y as usize)).is_some_and(|t| !t.blocked()))
+        .collect();
+    if open.is_empty() {
+        return "You can't see anything around you.".to_string();
+    }
+    let width = open.iter().map(|p| p.0).max().unwrap() - open.iter().map(|p| p.0).min().unwrap() + 1;
+    let depth = open.iter().map(|p| p.1).max().unwrap() - open.iter().map(|p| p.1).min().unwrap() + 1;
+    if width <= 2 && depth > 2 {
+        "You are in a passage running north to south.".to_string()
+    } else if depth <= 2 && width > 2 {
+        "You are in a passage running east to west.".to_string()
+    } else {
+        format!("You are in an open area about {} wide and {} deep.", width, depth)
+    }
+}
+
+/// what came into view, left it, or moved since the last description
+fn changes(game: &Game, now: &[Sighting], px: i32, py: i32) -> Vec<String> {
+    let previous = LAST_SEEN.with(|last| last.replace(Some((game.dungeon_level, now.to_vec()))));
+    let before = match previous {
+        Some((depth, before)) if depth == game.dungeon_level => before,
+        // new level (or first look): everything's new, which the rest of the description already says
+        _ => return Vec::new(),
+    };
+    // ids move around as things get picked up, so the name has to match too
+    let same = |a: &Sighting, b: &Sighting| a.target == b.target && a.name == b.name;
+    let is_entity = |s: &&Sighting| matches!(s.target, Target::Entity(_));
+
+    let mut lines = Vec::new();
+    for sighting in now.iter().filter(is_entity) {
+        match before.iter().find(|b| same(b, sighting)) {
+            None => lines.push(format!("New: {}.", placed(sighting, px, py))),
+            Some(old) if (old.x, old.y) != (sighting.x, sighting.y) => lines.push(format!("Moved: {}.", placed(sighting, px, py))),
+            Some(_) => {}
+        }
+    }
+    for gone in before.iter().filter(is_entity).filter(|b| !now.iter().any(|s| same(s, b))) {
+        lines.push(format!("Gone from view: {}.", gone.name));
+    }
+    lines
+}
+
+/// the whole description, one sentence or list per line
+pub fn describe(entities: &[Entity], game: &Game, seen: &HashSet<(i32, i32)>) -> Vec<String> {
+    let player = &entities[PLAYER];
+    let (px, py) = player.pos();
+    let hp = player.fighter.map_or(0, |f| f.hp);
+    let max_hp = player.get_max_hp();
+
+    let mut status = format!(
+        "Depth {}, turn {}. Health {} of {}, {}. Fighting with {}.",
+        game.dungeon_level,
+        game.turns,
+        hp,
+        max_hp,
+        health_state(hp, max_hp),
+        weapon_name(player, game)
+    );
+    if let Some(hunger) = player.hunger {
+        if hunger.state() != HungerState::NotHungry {
+            status.push_str(&format!(" You are {}.", hunger.state().to_string().to_lowercase()));
+        }
+    }
+    if player.poisoned > 0 {
+        status.push_str(" You are poisoned.");
+    }
+    let mut lines = vec![status, surroundings(game, seen)];
+
+    let sightings = look::in_view(entities, game, seen);
+    let here = game.map[px as usize][py as usize].terrain;
+    if here != Terrain::Floor {
+        lines.push(format!("You are standing on {}.", look::terrain_name(here)));
+    }
+    let is_exit = |s: &&Sighting| match s.target {
+        Target::Tile(x, y) => matches!(game.map[x as usize][y as usize].terrain, Terrain::Door(_) | Terrain::UpStairs | Terrain::DownStairs),
+        Target::Entity(_) => false,
+    };
+    let sentence = |label: &str, list: Vec<&Sighting>| -> Option<String> {
+        if list.is_empty() {
+            None
+        } else {
+            let items: Vec<String> = list.iter().map(|s| placed(s, px, py)).collect();
+            Some(format!("{}: {}.", label, items.join("; ")))
+        }
+    };
+    let monsters = sightings.iter().filter(|s| matches!(s.target, Target::Entity(id) if entities[id].fighter.is_some())).collect();
+    let things = sightings.iter().filter(|s| matches!(s.target, Target::Entity(id) if entities[id].fighter.is_none())).collect();
+    let exits = sightings.iter().filter(is_exit).collect();
+    let features = sightings.iter().filter(|s| matches!(s.target, Target::Tile(..)) && !is_exit(s)).collect();
+
+    lines.extend(sentence("Monsters", monsters));
+    lines.extend(sentence("Items", things));
+    lines.extend(sentence("Exits", exits));
+    lines.extend(sentence("Also here", features));
+
+    let changed = changes(game, &sightings, px, py);
+    if !changed.is_empty() {
+        lines.push(format!("Since last turn: {}", changed.join(" ")));
+    }
+    lines
+}
+
+#[test]
+fn narrative_tests() {
+    use crate::{fill_map, make_kobold, make_ration, Door, Tile};
+
+    let mut game = Game::new();
+    fill_map(&mut game.map, Tile::wall());
+    for x in 2..8 {
+        for y in 2..6 {
+            game.map[x][y] = Tile::empty();
+        }
+    }
+    game.map[5][1] = Tile::door(Door::Closed);
+    let player = Entity::new(3, 4, '@', "Player");
+    let mut entities = vec![player, make_kobold(5, 2, 3), make_ration(4, 4)];
+    let seen: HashSet<(i32, i32)> = (0..10).flat_map(|x| (0..10).map(move |y| (x, y))).collect();
+
+    LAST_SEEN.with(|last| *last.borrow_mut() = None);
+    let lines = describe(&entities, &game, &seen);
+    assert!(lines.contains(&"You are in an open area about 6 wide and 4 deep.".to_string()));
+    assert!(lines.contains(&"Monsters: kobold, 2 north 2 east.".to_string()));
+    assert!(lines.contains(&"Items: food ration, 1 east.".to_string()));
+    assert!(lines.contains(&"Exits: door, 3 north 2 east.".to_string()));
+    assert!(!lines.iter().any(|l| l.starts_with("Since last turn")));
+
+    entities[1].set_pos(4, 3);
+    entities.truncate(2);
+    let lines = describe(&entities, &game, &seen);
+    assert_eq!(lines.last().unwrap(), "Since last turn: Moved: kobold, 1 north 1 east. Gone from view: food ration.");
+}
@@ -0,0 +1,147 @@
+//! Overviews built from what's been explored: the current level squeezed down so it fits in a
+//! glance, and a summary of every level visited so far. Everything comes out of `Game` (the
+//! current map plus the levels left behind), so it works the same straight after loading.
+
+use std::collections::BTreeMap;
+
+use crate::look::{self, terrain_name};
+use crate::{Entity, Game, Map, Terrain, Tile, PLAYER};
+
+// how many map cells go into one overview cell
+const BLOCK_WIDTH: usize = 2;
+const BLOCK_HEIGHT: usize = 2;
+
+/// which tile in a block gets to stand for it; the higher the better, 0 is "never seen"
+fn importance(tile: &Tile) -> u8 {
+    if !tile.explored {
+        return 0;
+    }
+    if tile.trap.is_some_and(|t| !t.hidden) {
+        return 5;
+    }
+    match tile.terrain {
+        Terrain::UpStairs | Terrain::DownStairs => 6,
+        Terrain::Door(_) => 4,
+        Terrain::Floor => 2,
+        Terrain::Wall => 1,
+        _ => 3,
+    }
+}
+
+/// names of the explored features of a map, with how many tiles of each there are
+fn features(map: &Map) -> BTreeMap<String, usize> {
+    let mut found = BTreeMap::new();
+    for tile in map.iter().flatten().filter(|t| t.explored) {
+        let name = match tile.trap {
+            Some(trap) if !trap.hidden => trap.kind.to_string(),
+            _ if look::notable(tile.terrain) => terrain_name(tile.terrain).to_string(),
+            _ => continue,
+        };
+        *found.entry(name).or_insert(0) += 1;
+    }
+    found
+}
+
+/// the explored part of the current level, a block of tiles per character, with the player and
+/// where the stairs are underneath
+pub fn level_map(entities: &[Entity], game: &Game) -> Vec<String> {
+    let (px, py) = entities[PLAYER].pos();
+    let map = &game.map;
+    let width = map.len().div_ceil(BLOCK_WIDTH);
+    let height = map.first().map_or(0, |c| c.len()).div_ceil(BLOCK_HEIGHT);
+
+    let mut rows = Vec::new();
+    for by in 0..height {
+        let mut row = String::new();
+        for bx in 0..width {
+            let xs = bx * BLOCK_WIDTH..((bx + 1) * BLOCK_WIDTH).min(map.len());
+            let ys = by * BLOCK_HEIGHT..((by + 1) * BLOCK_HEIGHT).min(map[0].len());
+            if xs.contains(&(px as usize)) && ys.contains(&(py as usize)) {
+                row.push('@');
+                continue;
+            }
+            let best = xs
+                .flat_map(|x| ys.clone().map(move |y| &map[x][y]))
+                .max_by_key(|tile| importance(tile))
+                .filter(|tile| importance(tile) > 0);
+            row.push(best.map_or(' ', |tile| tile.glyph()));
+        }
+        rows.push(row);
+    }
+    // drop the rows nothing's been seen in, top and bottom
+    while rows.first().is_some_and(|r| r.trim().is_empty()) {
+        rows.remove(0);
+    }
+    while rows.last().is_some_and(|r| r.trim().is_empty()) {
+        rows.pop();
+    }
+
+    let mut lines = vec![format!("Dungeon level {} (one character for every {}x{} tiles):", game.dungeon_level, BLOCK_WIDTH, BLOCK_HEIGHT)];
+    lines.extend(rows);
+    for stairs in &[Terrain::UpStairs, Terrain::DownStairs] {
+        let place = (0..map.len())
+            .flat_map(|x| (0..map[x].len()).map(move |y| (x, y)))
+            .find(|&(x, y)| map[x][y].explored && map[x][y].terrain == *stairs);
+        if let Some((x, y)) = place {
+            lines.push(format!("{}: {}", terrain_name(*stairs), look::direction(x as i32 - px, y as i32 - py)));
+        }
+    }
+    let found = features(map);
+    if !found.is_empty() {
+        let names: Vec<&str> = found.keys().map(|name| name.as_str()).collect();
+        lines.push(format!("Found here: {}", names.join(", ")));
+    }
+    lines
+}
+
+/// one line per level visited, with what's been found there
+pub fn dungeon(game: &Game) -> Vec<String> {
+    let mut maps: BTreeMap<u32, &Map> = game.levels.iter().map(|(&depth, level)| (depth, &level.map)).collect();
+    maps.insert(game.dungeon_level, &game.map);
+
+    let mut lines = vec!["Levels visited:".to_string()];
+    for (depth, map) in maps {
+        let tiles = map.iter().flatten().count();
+        let explored = map.iter().flatten().filter(|t| t.explored).count();
+        let found: Vec<String> = features(map)
+            .into_iter()
+            .map(|(name, count)| if count > 1 { format!("{} x {}", count, name) } else { name })
+            .collect();
+        let here = if depth == game.dungeon_level { " (you are here)" } else { "" };
+        let found = if found.is_empty() { "nothing of note".to_string() } else { found.join(", ") };
+        lines.push(format!("{:3}{}: {}% explored; {}", depth, here, explored * 100 / tiles.max(1), found));
+    }
+    lines
+}
+
+#[test]
+fn overview_tests() {
+    use crate::{change_level, fill_map, Door};
+
+    let mut game = Game::new();
+    fill_map(&mut game.map, Tile::wall());
+    for x in 2..6 {
+        game.map[x][2] = Tile::empty();
+        game.map[x][2].explored = true;
+    }
+    game.map[6][2] = Tile::new(Terrain::DownStairs);
+    game.map[6][2].explored = true;
+    game.map[8][2] = Tile::door(Door::Closed);
+    game.map[8][2].explored = true;
+    let entities = vec![Entity::new(2, 2, '@', "Player")];
+
+    let lines = level_map(&entities, &game);
+    // the stairs win their block over the floor next to them, and unexplored rows are left out
+    assert_eq!(lines[1].trim_end(), " @.>+");
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[2], "stairs down: 4 east");
+    assert_eq!(lines[3], "Found here: door, stairs down");
+
+    let mut entities = entities;
+    change_level(&mut entities, &mut game, 2);
+    let lines = dungeon(&game);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("  1: "));
+    assert!(lines[1].ends_with("door, stairs down"));
+    assert!(lines[2].starts_with("  2 (you are here): 0% explored"));
+}
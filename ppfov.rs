@@ -129,6 +129,7 @@ fn view_tests() {
   yellow_purple_view.steep_line.collinear_p(2, 2);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn check_quadrant<VB, VE>(
   visited: &mut HashSet<(i32, i32)>, start_x: i32, start_y: i32, dir_x: i32, dir_y: i32, extent_x: i32, extent_y: i32, vision_blocked: &VB,
   visit_effect: &mut VE,
@@ -169,6 +170,7 @@ fn check_quadrant<VB, VE>(
   }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn visit_coord<VB, VE>(
   visited: &mut HashSet<(i32, i32)>, start_x: i32, start_y: i32, dir_x: i32, dir_y: i32, vision_blocked: &VB, visit_effect: &mut VE, offset_x: i32,
   offset_y: i32, active_views: &mut Vec<View>,
@@ -262,28 +264,28 @@ where
 {
   debug_assert!(radius >= 0, "ppfov: vision radius must be non-negative, got {}", radius);
   debug_assert!(
-    start_x.saturating_add(radius) < ::std::i32::MAX,
+    start_x.saturating_add(radius) < i32::MAX,
     "ppfov: Location ({},{}) with radius {} would cause overflow problems!",
     start_x,
     start_y,
     radius
   );
   debug_assert!(
-    start_y.saturating_add(radius) < ::std::i32::MAX,
+    start_y.saturating_add(radius) < i32::MAX,
     "ppfov: Location ({},{}) with radius {} would cause overflow problems!",
     start_x,
     start_y,
     radius
   );
   debug_assert!(
-    start_x.saturating_sub(radius) > ::std::i32::MIN,
+    start_x.saturating_sub(radius) > i32::MIN,
     "ppfov: Location ({},{}) with radius {} would cause underflow problems!",
     start_x,
     start_y,
     radius
   );
   debug_assert!(
-    start_y.saturating_sub(radius) > ::std::i32::MIN,
+    start_y.saturating_sub(radius) > i32::MIN,
     "ppfov: Location ({},{}) with radius {} would cause underflow problems!",
     start_x,
     start_y,
@@ -0,0 +1,471 @@
+//! Drawing the screen: the map and everything on it go into a grid of cells by coordinate,
+//! then the grid is turned into text in one go, either plain or with ANSI colours.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use crate::term;
+use crate::tiles;
+use crate::{Entity, Item, Map, Terrain, MAP_HEIGHT, MAP_WIDTH, PLAYER};
+
+// whether we print ANSI colour codes, see detect_color
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+// how much of the map fits on the screen, in cells
+static VIEW_WIDTH: AtomicI32 = AtomicI32::new(40);
+static VIEW_HEIGHT: AtomicI32 = AtomicI32::new(20);
+
+// only send the lines that changed since the last frame, see present
+static DIFF: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // the last frame sent, one string per screen line; None means the next one goes out in full
+    static LAST_FRAME: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+pub fn set_view_size(width: i32, height: i32) {
+    VIEW_WIDTH.store(width.max(1), Ordering::Relaxed);
+    VIEW_HEIGHT.store(height.max(1), Ordering::Relaxed);
+}
+
+pub fn view_size() -> (i32, i32) {
+    (VIEW_WIDTH.load(Ordering::Relaxed), VIEW_HEIGHT.load(Ordering::Relaxed))
+}
+
+pub fn set_color(on: bool) {
+    COLOR.store(on, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+pub fn set_diff(on: bool) {
+    DIFF.store(on, Ordering::Relaxed);
+}
+
+pub fn diff_enabled() -> bool {
+    DIFF.load(Ordering::Relaxed)
+}
+
+/// send the next frame in full, e.g. when something else has been printed over the screen
+pub fn forget_frame() {
+    LAST_FRAME.with(|last| *last.borrow_mut() = None);
+}
+
+/// colour by default only on a real terminal that can do it, and not if the user asked for none
+/// (see https://no-color.org)
+pub fn detect_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let dumb = std::env::var("TERM").map_or(true, |term| term == "dumb");
+    !no_color && !dumb && std::io::stdout().is_terminal()
+}
+
+/// the 16 standard ANSI colours, in code order (not all of them are used yet)
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl Color {
+    fn fg_code(self) -> u8 {
+        let n = self as u8;
+        if n < 8 {
+            30 + n
+        } else {
+            90 + n - 8
+        }
+    }
+
+    fn bg_code(self) -> u8 {
+        self.fg_code() + 10
+    }
+}
+
+/// the escape sequence that switches to these colours
+fn sgr(fg: Color, bg: Option<Color>) -> String {
+    match bg {
+        Some(bg) => format!("\x1b[{};{}m", fg.fg_code(), bg.bg_code()),
+        None => format!("\x1b[{}m", fg.fg_code()),
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+/// wrap text in a colour, if colour is on
+pub fn paint(text: &str, fg: Color) -> String {
+    if color_enabled() {
+        format!("{}{}{}", sgr(fg, None), text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// what gets drawn on top of what, lowest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Floor,
+    Items,
+    Corpses,
+    Monsters,
+    Player,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Color,
+    pub bg: Option<Color>, //None leaves the terminal's background
+    pub remembered: bool, //explored earlier, but not in view right now
+    pub layer: Layer,
+}
+
+impl Cell {
+    pub fn new(glyph: char, layer: Layer) -> Self {
+        Cell { glyph, fg: Color::White, bg: None, remembered: false, layer }
+    }
+
+    /// the colours it's actually shown in; what we only remember is greyed out
+    fn colors(&self) -> (Color, Option<Color>) {
+        if self.remembered {
+            (Color::BrightBlack, None)
+        } else {
+            (self.fg, self.bg)
+        }
+    }
+
+    pub fn blank() -> Self {
+        Cell::new(' ', Layer::Floor)
+    }
+}
+
+pub struct Buffer {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>, //row by row
+}
+
+impl Buffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        Buffer { width, height, cells: vec![Cell::blank(); (width * height) as usize] }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            Some((y * self.width + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// draw a cell, unless it's off the buffer or something on a higher layer is already there
+    pub fn put(&mut self, x: i32, y: i32, cell: Cell) {
+        if let Some(i) = self.index(x, y) {
+            if cell.layer >= self.cells[i].layer {
+                self.cells[i] = cell;
+            }
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// one line of text per row
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity(self.cells.len() + self.height as usize);
+        for row in self.cells.chunks(self.width as usize) {
+            text.extend(row.iter().map(|cell| cell.glyph));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// like to_text, with colour codes only where the colour changes
+    pub fn to_ansi(&self) -> String {
+        let mut text = String::with_capacity(self.cells.len() * 4);
+        for row in self.cells.chunks(self.width as usize) {
+            let mut current = None;
+            for cell in row {
+                let colors = cell.colors();
+                if current != Some(colors) {
+                    text.push_str(RESET);
+                    text.push_str(&sgr(colors.0, colors.1));
+                    current = Some(colors);
+                }
+                text.push(cell.glyph);
+            }
+            text.push_str(RESET);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// The part of the map on screen. Map coordinates and screen coordinates are kept apart:
+/// everything in the game works in map coordinates, and only drawing goes through the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub x: i32, //map position of the top left corner of the screen
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Camera {
+    /// centred on the given map position, but never showing anything past the edges of the map
+    pub fn centred_on(x: i32, y: i32, width: i32, height: i32) -> Self {
+        let width = width.min(MAP_WIDTH);
+        let height = height.min(MAP_HEIGHT);
+        Camera {
+            x: (x - width / 2).clamp(0, MAP_WIDTH - width),
+            y: (y - height / 2).clamp(0, MAP_HEIGHT - height),
+            width,
+            height,
+        }
+    }
+
+    /// where a map position shows up on screen, if it does
+    pub fn to_screen(self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (sx, sy) = (x - self.x, y - self.y);
+        if sx >= 0 && sx < self.width && sy >= 0 && sy < self.height {
+            Some((sx, sy))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_map(self, sx: i32, sy: i32) -> (i32, i32) {
+        (sx + self.x, sy + self.y)
+    }
+}
+
+/// which layer an entity is drawn on
+pub fn layer_of(id: usize, entity: &Entity) -> Layer {
+    if id == PLAYER {
+        Layer::Player
+    } else if entity.fighter.is_some() {
+        Layer::Monsters
+    } else if entity.item.is_some() {
+        Layer::Items
+    } else {
+        Layer::Corpses
+    }
+}
+
+/// the colour an entity is drawn in
+pub fn entity_color(id: usize, entity: &Entity) -> Color {
+    match layer_of(id, entity) {
+        Layer::Player => Color::BrightWhite,
+        Layer::Monsters => Color::BrightRed,
+        Layer::Corpses => Color::Red,
+        Layer::Items => match entity.item {
+            // all potions and all scrolls look alike, or the colour would give them away
+            Some(Item::Heal) | Some(Item::Poison) => Color::BrightMagenta,
+            Some(Item::Identify) | Some(Item::Mapping) => Color::BrightCyan,
+            Some(Item::Food(_)) => Color::Yellow,
+            Some(Item::Key) => Color::BrightYellow,
+            _ => Color::Cyan,
+        },
+        Layer::Floor => Color::White,
+    }
+}
+
+/// a wall joined up to the walls next to it, as far as the player knows about them
+fn wall_glyph(map: &Map, x: i32, y: i32) -> char {
+    let joins = |dx: i32, dy: i32| {
+        let (nx, ny) = (x + dx, y + dy);
+        (0..MAP_WIDTH).contains(&nx) && (0..MAP_HEIGHT).contains(&ny) && {
+            let tile = &map[nx as usize][ny as usize];
+            tile.terrain == Terrain::Wall && tile.explored
+        }
+    };
+    tiles::tileset().wall(joins(0, -1), joins(0, 1), joins(1, 0), joins(-1, 0))
+}
+
+/// the tiles in view, and the ones we remember
+pub fn draw_map(buffer: &mut Buffer, map: &Map, seen: &HashSet<(i32, i32)>, camera: &Camera) {
+    for sy in 0..camera.height {
+        for sx in 0..camera.width {
+            let (x, y) = camera.to_map(sx, sy);
+            let tile = &map[x as usize][y as usize];
+            let in_view = seen.contains(&(x, y));
+            if in_view || tile.explored {
+                let (fg, bg) = tile.colors();
+                let glyph = if tile.terrain == Terrain::Wall { wall_glyph(map, x, y) } else { tile.glyph() };
+                buffer.put(sx, sy, Cell { glyph, fg, bg, remembered: !in_view, layer: Layer::Floor });
+            }
+        }
+    }
+}
+
+/// entities only show up while we can see them
+pub fn draw_entities(buffer: &mut Buffer, entities: &[Entity], seen: &HashSet<(i32, i32)>, camera: &Camera) {
+    for (id, entity) in entities.iter().enumerate() {
+        if !seen.contains(&entity.pos()) {
+            continue;
+        }
+        if let Some((sx, sy)) = camera.to_screen(entity.x, entity.y) {
+            let cell = Cell { fg: entity_color(id, entity), ..Cell::new(tiles::tileset().entity(entity), layer_of(id, entity)) };
+            buffer.put(sx, sy, cell);
+        }
+    }
+}
+
+/// the view around the player, at the configured size
+pub fn render(entities: &[Entity], map: &Map, seen: &HashSet<(i32, i32)>) -> Buffer {
+    let (width, height) = view_size();
+    let camera = Camera::centred_on(entities[PLAYER].x, entities[PLAYER].y, width, height);
+    let mut buffer = Buffer::new(camera.width, camera.height);
+    draw_map(&mut buffer, map, seen, &camera);
+    draw_entities(&mut buffer, entities, seen, &camera);
+    buffer
+}
+
+/// which lines differ between two frames, counting lines only one of them has
+pub fn changed_rows(previous: &[String], current: &[String]) -> Vec<usize> {
+    (0..current.len()).filter(|&row| previous.get(row) != current.get(row)).collect()
+}
+
+/// What to send for a frame. Without a previous frame that's all of it; otherwise in raw mode
+/// the changed lines get rewritten in place with cursor moves, and in line mode only the changed
+/// lines are printed, each after its row number.
+fn frame_text(previous: Option<&[String]>, current: &[String], raw: bool) -> String {
+    let mut text = String::new();
+    match previous {
+        None => {
+            if raw {
+                text.push_str(&term::cursor_to(0, 0));
+                text.push_str(term::CLEAR_BELOW);
+            }
+            for line in current {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        Some(previous) if raw => {
+            for row in changed_rows(previous, current) {
+                text.push_str(&term::cursor_to(0, row as i32));
+                text.push_str(&current[row]);
+                text.push_str(term::CLEAR_LINE);
+            }
+            // and get rid of last turn's messages and prompt underneath
+            text.push_str(&term::cursor_to(0, current.len() as i32));
+            text.push_str(term::CLEAR_BELOW);
+        }
+        Some(previous) => {
+            for row in changed_rows(previous, current) {
+                text.push_str(&format!("{:2}|{}\n", row, current[row]));
+            }
+        }
+    }
+    text
+}
+
+/// print a frame, all of it or only what changed depending on --diff
+pub fn present(lines: Vec<String>) {
+    if !diff_enabled() {
+        for line in &lines {
+            println!("{}", line);
+        }
+        return;
+    }
+    let text = LAST_FRAME.with(|last| {
+        let text = frame_text(last.borrow().as_deref(), &lines, term::is_raw());
+        *last.borrow_mut() = Some(lines);
+        text
+    });
+    print!("{}", text);
+}
+
+#[test]
+fn layer_tests() {
+    use crate::{make_ration, Game, Tile};
+
+    let mut game = Game::new();
+    game.map[3][3] = Tile::empty();
+    game.map[4][3] = Tile::empty();
+    let player = Entity::new(3, 3, '@', "Player");
+    let mut corpse = Entity::new(4, 3, '%', "remains of kobold");
+    corpse.blocks = false;
+    // the ration is listed after the player and the corpse, but still ends up underneath
+    let entities = vec![player, corpse, make_ration(3, 3), make_ration(4, 3)];
+    let seen: HashSet<(i32, i32)> = vec![(3, 3), (4, 3)].into_iter().collect();
+
+    let buffer = render(&entities, &game.map, &seen);
+    let cell = |x, y| buffer.cells[buffer.index(x, y).unwrap()];
+    assert_eq!(cell(3, 3).glyph, '@');
+    assert_eq!(cell(4, 3).glyph, '%');
+    assert_eq!(cell(4, 3).layer, Layer::Corpses);
+    let text = buffer.to_text();
+    // the default view is smaller than the map, so that's all that gets drawn
+    let (width, height) = view_size();
+    assert!(width < MAP_WIDTH && height < MAP_HEIGHT);
+    assert_eq!(text.lines().count(), height as usize);
+    assert!(text.lines().all(|line| line.chars().count() == width as usize));
+
+    // the same glyphs come out with the colour codes stripped
+    let ansi = buffer.to_ansi();
+    let mut stripped = String::new();
+    let mut in_escape = false;
+    for c in ansi.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if !in_escape => stripped.push(c),
+            _ => {}
+        }
+    }
+    assert_eq!(stripped, text);
+    assert_eq!(Color::BrightRed.fg_code(), 91);
+    assert_eq!(Color::Blue.bg_code(), 44);
+}
+
+#[test]
+fn camera_tests() {
+    // centred where there's room, pushed back from the edges where there isn't
+    let camera = Camera::centred_on(10, 10, 6, 4);
+    assert_eq!((camera.x, camera.y), (7, 8));
+    assert_eq!(camera.to_screen(10, 10), Some((3, 2)));
+    assert_eq!(camera.to_map(3, 2), (10, 10));
+    assert_eq!(camera.to_screen(6, 10), None);
+
+    let corner = Camera::centred_on(0, MAP_HEIGHT - 1, 6, 4);
+    assert_eq!((corner.x, corner.y), (0, MAP_HEIGHT - 4));
+
+    // a view bigger than the map just shows the map
+    let whole = Camera::centred_on(5, 5, 100, 100);
+    assert_eq!((whole.x, whole.y, whole.width, whole.height), (0, 0, MAP_WIDTH, MAP_HEIGHT));
+}
+
+#[test]
+fn diff_tests() {
+    let frame = |rows: &[&str]| -> Vec<String> { rows.iter().map(|r| r.to_string()).collect() };
+    let first = frame(&["#####", "#.@.#", "#####"]);
+    let second = frame(&["#####", "#..@#", "#####", "You hit the kobold."]);
+    assert_eq!(changed_rows(&first, &second), vec![1, 3]);
+    assert_eq!(changed_rows(&second, &second), Vec::<usize>::new());
+
+    assert_eq!(frame_text(None, &first, false), "#####\n#.@.#\n#####\n");
+    assert_eq!(frame_text(Some(&first), &second, false), " 1|#..@#\n 3|You hit the kobold.\n");
+    // raw mode: straight to the changed lines, then clear out whatever was under the last frame
+    assert_eq!(frame_text(Some(&first), &second, true), "\x1b[2;1H#..@#\x1b[K\x1b[4;1HYou hit the kobold.\x1b[K\x1b[5;1H\x1b[J");
+}
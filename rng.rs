@@ -0,0 +1,44 @@
+//! All the game's random numbers come from here. Every level is generated right after
+//! reseeding from the run's seed, so the same seed gives the same dungeon.
+
+use std::cell::RefCell;
+
+use rand::distributions::range::SampleRange;
+use rand::{Rng, SeedableRng, StdRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::new().expect("could not seed the random number generator"));
+}
+
+/// a fresh seed for a new run
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+pub fn reseed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::from_seed(&[seed as usize, (seed >> 32) as usize][..]));
+}
+
+/// the seed to generate a given depth of a run with
+pub fn level_seed(seed: u64, depth: u32) -> u64 {
+    seed ^ u64::from(depth).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// a number from low up to, but not including, high
+pub fn gen_range<T: PartialOrd + SampleRange>(low: T, high: T) -> T {
+    RNG.with(|rng| rng.borrow_mut().gen_range(low, high))
+}
+
+pub fn shuffle<T>(values: &mut [T]) {
+    RNG.with(|rng| rng.borrow_mut().shuffle(values))
+}
+
+#[test]
+fn seed_tests() {
+    reseed(level_seed(1234, 2));
+    let first: Vec<i32> = (0..10).map(|_| gen_range(0, 1000)).collect();
+    reseed(level_seed(1234, 2));
+    let again: Vec<i32> = (0..10).map(|_| gen_range(0, 1000)).collect();
+    assert_eq!(first, again);
+    assert_ne!(level_seed(1234, 2), level_seed(1234, 3));
+}
@@ -0,0 +1,582 @@
+//! Save file format: the game state wrapped in an envelope that says which version wrote it,
+//! plus the migrations that bring older saves up to the current structs.
+//! Saves live in named slots, one <slot>.save file each in the save directory. The original
+//! game's single `savegame` file is imported into a slot once (see import_legacy).
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::{Entity, Game, MAP_HEIGHT, MAP_WIDTH, PLAYER};
+
+/// Bump this (and add a migration below) whenever a change to the saved structs would break old saves.
+pub const FORMAT_VERSION: u32 = 6;
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const SAVE_DIR: &str = "saves";
+/// where the original game kept its one save, a bare (entities, game) tuple
+pub const LEGACY_SAVE: &str = "savegame";
+
+/// MIGRATIONS[n] upgrades a version n save to version n + 1
+const MIGRATIONS: [fn(Value) -> Result<Value, SaveError>; FORMAT_VERSION as usize] = [migrate_0_to_1, migrate_1_to_2, migrate_2_to_3, migrate_3_to_4, migrate_4_to_5, migrate_5_to_6];
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format_version: u32,
+    game_version: String,
+    #[serde(default)]
+    checksum: Option<String>, //of the data; every save since the envelope was added should have one
+    #[serde(default)]
+    summary: Option<Summary>, //for the slot list, so it doesn't have to load the whole game
+    data: Value,
+}
+
+/// just the start of the envelope, for listing slots
+#[derive(Deserialize)]
+struct Header {
+    format_version: u32,
+    game_version: String,
+    #[serde(default)]
+    summary: Option<Summary>,
+}
+
+#[derive(Serialize)]
+struct SaveRef<'a> {
+    entities: &'a [Entity],
+    game: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct SaveData {
+    entities: Vec<Entity>,
+    game: Game,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// the file couldn't be read or written
+    Io(io::Error),
+    /// not valid JSON, or doesn't match what the version says it should look like
+    Corrupt(String),
+    /// the data doesn't match its checksum, so it was damaged or edited by hand
+    BadChecksum,
+    /// it loaded, but the game inside makes no sense
+    Invalid(String),
+    /// written by a newer version of the game than this one
+    TooNew { format_version: u32, game_version: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) if err.kind() == io::ErrorKind::NotFound => write!(f, "there is no such saved game"),
+            SaveError::Io(err) => write!(f, "the save file could not be accessed ({})", err),
+            SaveError::Corrupt(reason) => write!(f, "the save file is damaged ({})", reason),
+            SaveError::BadChecksum => write!(f, "the save file is damaged or has been edited"),
+            SaveError::Invalid(reason) => write!(f, "the saved game is not valid ({})", reason),
+            SaveError::TooNew { format_version, game_version } => write!(
+                f,
+                "the save was made by game version {} (save format {}), but this is version {} which only understands up to format {}",
+                game_version, format_version, GAME_VERSION, FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Corrupt(err.to_string())
+    }
+}
+
+/// What can go wrong with the other files kept next to the saves (the scores and the config)
+#[derive(Debug)]
+pub enum FileError {
+    /// the file couldn't be read or written
+    Io(io::Error),
+    /// not valid JSON, or not what the file should look like
+    Corrupt(String),
+    /// written by a newer version of the game, in a format this one doesn't know
+    TooNew { format_version: u32, supported: u32 },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(err) => write!(f, "the file could not be accessed ({})", err),
+            FileError::Corrupt(reason) => write!(f, "the file is damaged ({})", reason),
+            FileError::TooNew { format_version, supported } => write!(
+                f,
+                "the file is in format {}, but this version of the game only understands up to format {}",
+                format_version, supported
+            ),
+        }
+    }
+}
+
+impl Error for FileError {}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        FileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FileError {
+    fn from(err: serde_json::Error) -> Self {
+        FileError::Corrupt(err.to_string())
+    }
+}
+
+/// What the slot listing shows about a saved game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub name: String,
+    pub depth: u32,
+    pub turns: u32,
+}
+
+pub fn to_json(entities: &[Entity], game: &Game) -> Result<String, SaveError> {
+    let data = serde_json::to_value(SaveRef { entities, game })?;
+    let envelope = Envelope {
+        format_version: FORMAT_VERSION,
+        game_version: GAME_VERSION.to_string(),
+        checksum: Some(checksum(&data)),
+        summary: entities.get(PLAYER).map(|player| Summary { name: player.name.clone(), depth: game.dungeon_level, turns: game.turns }),
+        data,
+    };
+    Ok(serde_json::to_string(&envelope)?)
+}
+
+pub fn from_json(json: &str) -> Result<(Vec<Entity>, Game), SaveError> {
+    let raw: Value = serde_json::from_str(json)?;
+    if raw.is_array() {
+        return Err(SaveError::Corrupt("this is an old-style save, which has to be imported".to_string()));
+    }
+    let envelope: Envelope = serde_json::from_value(raw)?;
+    if envelope.format_version > FORMAT_VERSION {
+        return Err(SaveError::TooNew { format_version: envelope.format_version, game_version: envelope.game_version });
+    }
+    // no checksum means it was tampered with too, whatever version it claims to be
+    if envelope.checksum != Some(checksum(&envelope.data)) {
+        return Err(SaveError::BadChecksum);
+    }
+    upgrade(envelope.data, envelope.format_version)
+}
+
+/// the original save format, with no envelope (and so no checksum); only import_legacy reads these
+fn from_legacy_json(json: &str) -> Result<(Vec<Entity>, Game), SaveError> {
+    let raw: Value = serde_json::from_str(json)?;
+    if !raw.is_array() {
+        return Err(SaveError::Corrupt("expected an (entities, game) pair".to_string()));
+    }
+    upgrade(raw, 0)
+}
+
+/// run the migrations from the given version on, then check the result makes sense
+fn upgrade(mut data: Value, format_version: u32) -> Result<(Vec<Entity>, Game), SaveError> {
+    for migration in &MIGRATIONS[format_version as usize..] {
+        data = migration(data)?;
+    }
+    let save: SaveData = serde_json::from_value(data)?;
+    validate(&save.entities, &save.game)?;
+    Ok((save.entities, save.game))
+}
+
+/// FNV-1a over the serialized data; serde_json keeps object keys sorted, so this is stable across a load and save
+fn checksum(data: &Value) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data.to_string().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// catch saves that parse fine but would crash the game
+fn validate(entities: &[Entity], game: &Game) -> Result<(), SaveError> {
+    let invalid = |reason: &str| Err(SaveError::Invalid(reason.to_string()));
+    let player = match entities.get(PLAYER) {
+        Some(player) => player,
+        None => return invalid("there is no player"),
+    };
+    if player.fighter.is_none() {
+        return invalid("the player can't fight");
+    }
+    if game.dungeon_level == 0 {
+        return invalid("the dungeon level is 0");
+    }
+    let maps = std::iter::once(&game.map).chain(game.levels.values().map(|level| &level.map));
+    for map in maps {
+        if map.len() != MAP_WIDTH as usize || map.iter().any(|column| column.len() != MAP_HEIGHT as usize) {
+            return invalid("a map is the wrong size");
+        }
+    }
+    let everyone = entities.iter().chain(game.levels.values().flat_map(|level| level.entities.iter()));
+    for entity in everyone {
+        if entity.x < 0 || entity.x >= MAP_WIDTH || entity.y < 0 || entity.y >= MAP_HEIGHT {
+            return invalid("something is off the map");
+        }
+    }
+    Ok(())
+}
+
+/// turn a character name into something safe to use as a file name
+pub fn slot_name(name: &str) -> String {
+    let slot: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if slot.is_empty() {
+        "save".to_string()
+    } else {
+        slot
+    }
+}
+
+fn slot_path(slot: &str) -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(format!("{}.save", slot))
+}
+
+pub fn slot_exists(slot: &str) -> bool {
+    slot_path(slot).exists()
+}
+
+/// a slot named after the character that isn't taken yet, so we don't clobber somebody else's save
+pub fn free_slot(name: &str) -> String {
+    let base = slot_name(name);
+    let mut slot = base.clone();
+    let mut n = 2;
+    while slot_exists(&slot) {
+        slot = format!("{}-{}", base, n);
+        n += 1;
+    }
+    slot
+}
+
+/// Move a save left by the original game into a slot of its own. Returns the slot, or None if
+/// there was nothing to import. The old file is renamed rather than deleted, and only once the
+/// new save is safely written.
+pub fn import_legacy() -> Result<Option<String>, SaveError> {
+    let json = match fs::read_to_string(LEGACY_SAVE) {
+        Ok(json) => json,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let (entities, game) = from_legacy_json(&json)?;
+    let slot = free_slot(&entities[PLAYER].name);
+    save(&slot, &entities, &game)?;
+    fs::rename(LEGACY_SAVE, format!("{}.imported", LEGACY_SAVE))?;
+    Ok(Some(slot))
+}
+
+pub fn save(slot: &str, entities: &[Entity], game: &Game) -> Result<(), SaveError> {
+    fs::create_dir_all(SAVE_DIR)?;
+    // write next to it and swap it in, so a crash halfway doesn't destroy the old save
+    let path = slot_path(slot);
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, to_json(entities, game)?)?;
+    fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+/// a slot that was never written is as good as deleted
+pub fn delete(slot: &str) -> Result<(), SaveError> {
+    match fs::remove_file(slot_path(slot)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(SaveError::Io(err)),
+        _ => Ok(()),
+    }
+}
+
+pub fn load(slot: &str) -> Result<(Vec<Entity>, Game), SaveError> {
+    from_json(&fs::read_to_string(slot_path(slot))?)
+}
+
+/// Slots used to be <slot>.json, next to scores.json and config.json. Give any left over the
+/// .save extension so they're listed again; returns how many there were.
+pub fn rename_old_slots() -> Result<usize, SaveError> {
+    let dir = match fs::read_dir(SAVE_DIR) {
+        Ok(dir) => dir,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let mut renamed = 0;
+    for path in dir.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if !path.extension().is_some_and(|ext| ext == "json") {
+            continue;
+        }
+        // only a save says which game version wrote it, so this leaves the scores and config be
+        let is_save = fs::read_to_string(&path).ok().and_then(|json| serde_json::from_str::<Header>(&json).ok()).is_some();
+        let new_path = path.with_extension("save");
+        if is_save && !new_path.exists() {
+            fs::rename(&path, &new_path)?;
+            renamed += 1;
+        }
+    }
+    Ok(renamed)
+}
+
+/// every slot in the save directory, sorted by name, with what's in it or why it won't load
+pub fn list_slots() -> Vec<(String, Result<Summary, SaveError>)> {
+    let mut slots: Vec<String> = match fs::read_dir(SAVE_DIR) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "save"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    slots.sort();
+    slots
+        .into_iter()
+        .map(|slot| {
+            let summary = summary(&slot);
+            (slot, summary)
+        })
+        .collect()
+}
+
+/// what's in a slot, from the envelope if it says; saves from before it did get loaded in full
+fn summary(slot: &str) -> Result<Summary, SaveError> {
+    let json = fs::read_to_string(slot_path(slot))?;
+    let header: Header = serde_json::from_str(&json)?;
+    if header.format_version > FORMAT_VERSION {
+        return Err(SaveError::TooNew { format_version: header.format_version, game_version: header.game_version });
+    }
+    match header.summary {
+        Some(summary) => Ok(summary),
+        None => from_json(&json).map(|(entities, game)| Summary {
+            name: entities[PLAYER].name.clone(),
+            depth: game.dungeon_level,
+            turns: game.turns,
+        }),
+    }
+}
+
+/// Version 0 was the original save: a bare (entities, game) tuple. Back then tiles were three flags
+/// instead of a terrain kind, the player's inventory lived in the game rather than on the player,
+/// and entities, equipment and the game had none of the fields added since. Fields that are
+/// already there (from saves made part way through those changes) are left alone.
+fn migrate_0_to_1(data: Value) -> Result<Value, SaveError> {
+    let (mut entities, mut game) = match data {
+        Value::Array(mut tuple) if tuple.len() == 2 => {
+            let game = tuple.pop().unwrap();
+            (tuple.pop().unwrap(), game)
+        }
+        _ => return Err(SaveError::Corrupt("expected an (entities, game) pair".to_string())),
+    };
+
+    let game_fields = game.as_object_mut().ok_or_else(|| SaveError::Corrupt("game is not an object".to_string()))?;
+    game_fields.entry("levels").or_insert_with(|| json!({}));
+    game_fields.entry("appearances").or_insert_with(|| json!({}));
+    game_fields.entry("identified").or_insert_with(|| json!([]));
+    if let Some(Value::Array(columns)) = game_fields.get_mut("map") {
+        for tile in columns.iter_mut().filter_map(Value::as_array_mut).flatten() {
+            if let Some(tile) = tile.as_object_mut() {
+                upgrade_tile(tile);
+            }
+        }
+    }
+    let carried = game_fields.remove("inventory");
+
+    let entities_list = entities.as_array_mut().ok_or_else(|| SaveError::Corrupt("entities is not a list".to_string()))?;
+    for entity in entities_list.iter_mut() {
+        upgrade_entity(entity);
+    }
+    if let Some(player) = entities_list.get_mut(PLAYER).and_then(Value::as_object_mut) {
+        // what used to be the game's inventory was always the player's
+        if let Some(Value::Array(mut items)) = carried {
+            items.iter_mut().for_each(upgrade_entity);
+            if let Some(Value::Array(inventory)) = player.get_mut("inventory") {
+                inventory.extend(items);
+            }
+        }
+        // and the player has been hungry ever since
+        match player.get("hunger") {
+            Some(hunger) if !hunger.is_null() => {}
+            _ => {
+                player.insert("hunger".to_string(), json!({ "nutrition": crate::START_NUTRITION }));
+            }
+        }
+    }
+
+    Ok(json!({ "entities": entities, "game": game }))
+}
+
+/// blocked/block_sight/stairs flags to a terrain kind; the only things there were walls, floor and stairs down
+fn upgrade_tile(tile: &mut serde_json::Map<String, Value>) {
+    let flag = |tile: &serde_json::Map<String, Value>, name: &str| tile.get(name).and_then(Value::as_bool).unwrap_or(false);
+    if !tile.contains_key("terrain") {
+        let terrain = if flag(tile, "stairs") {
+            "DownStairs"
+        } else if flag(tile, "blocked") {
+            "Wall"
+        } else {
+            "Floor"
+        };
+        tile.insert("terrain".to_string(), json!(terrain));
+    }
+    for old in &["blocked", "block_sight", "stairs"] {
+        tile.remove(*old);
+    }
+    tile.entry("explored").or_insert(Value::Bool(false));
+}
+
+/// the entity fields added since version 0, with what an entity was like before they existed
+fn upgrade_entity(entity: &mut Value) {
+    let fields = match entity.as_object_mut() {
+        Some(fields) => fields,
+        None => return,
+    };
+    fields.entry("inventory").or_insert_with(|| json!([]));
+    fields.entry("quantity").or_insert(json!(1));
+    fields.entry("swims").or_insert(json!(false));
+    fields.entry("slowed").or_insert(json!(0));
+    fields.entry("poisoned").or_insert(json!(0));
+    if let Some(Value::Object(equipment)) = fields.get_mut("equipment") {
+        equipment.entry("two_handed").or_insert(json!(false));
+        equipment.entry("defense_bonus").or_insert(json!(0));
+        equipment.entry("max_hp_bonus").or_insert(json!(0));
+    }
+    if let Some(Value::Array(items)) = fields.get_mut("inventory") {
+        items.iter_mut().for_each(upgrade_entity);
+    }
+}
+
+/// the game part of a version 1+ save, to add fields to
+fn game_fields(data: &mut Value) -> Result<&mut serde_json::Map<String, Value>, SaveError> {
+    data.get_mut("game").and_then(Value::as_object_mut).ok_or_else(|| SaveError::Corrupt("game is not an object".to_string()))
+}
+
+/// version 2 added the turn counter and the checksum
+fn migrate_1_to_2(mut data: Value) -> Result<Value, SaveError> {
+    let game = game_fields(&mut data)?;
+    game.entry("turns").or_insert(json!(0));
+    Ok(data)
+}
+
+/// version 3 added casual mode; older games were always played with permadeath
+fn migrate_2_to_3(mut data: Value) -> Result<Value, SaveError> {
+    let game = game_fields(&mut data)?;
+    game.entry("casual").or_insert(json!(false));
+    Ok(data)
+}
+
+/// version 4 added the kill list (and what killed an entity, which is optional so needs no help)
+fn migrate_3_to_4(mut data: Value) -> Result<Value, SaveError> {
+    let game = game_fields(&mut data)?;
+    game.entry("kills").or_insert(json!({}));
+    Ok(data)
+}
+
+/// version 5 added the run's seed (0 when we don't know it) and XP
+fn migrate_4_to_5(mut data: Value) -> Result<Value, SaveError> {
+    let game = game_fields(&mut data)?;
+    game.entry("seed").or_insert(json!(0));
+    game.entry("xp").or_insert(json!(0));
+    Ok(data)
+}
+
+/// version 6 made the map bigger; older levels keep their layout in the top left corner, with
+/// solid rock (that hasn't been seen yet) filling out the rest
+fn migrate_5_to_6(mut data: Value) -> Result<Value, SaveError> {
+    let game = game_fields(&mut data)?;
+    if let Some(map) = game.get_mut("map") {
+        enlarge_map(map);
+    }
+    if let Some(Value::Object(levels)) = game.get_mut("levels") {
+        for map in levels.values_mut().filter_map(|level| level.get_mut("map")) {
+            enlarge_map(map);
+        }
+    }
+    Ok(data)
+}
+
+fn enlarge_map(map: &mut Value) {
+    let rock = json!({ "terrain": "Wall", "trap": null, "explored": false });
+    let (width, height) = (MAP_WIDTH as usize, MAP_HEIGHT as usize);
+    if let Value::Array(columns) = map {
+        for column in columns.iter_mut().filter_map(Value::as_array_mut) {
+            if column.len() < height {
+                column.resize(height, rock.clone());
+            }
+        }
+        if columns.len() < width {
+            columns.resize(width, Value::Array(vec![rock; height]));
+        }
+    }
+}
+
+#[test]
+fn migration_tests() {
+    use crate::{test_player, Terrain};
+
+    let entities = vec![test_player(1, 1)];
+    let game = Game::new();
+
+    // a save written by the very first version of the game: a short walk to pick up and wield the sword
+    let baseline = include_str!("fixtures/baseline-savegame.json");
+    let (loaded, loaded_game) = from_legacy_json(baseline).unwrap();
+    assert_eq!(loaded[PLAYER].name, "Player");
+    assert_eq!(loaded[PLAYER].fighter.unwrap().hp, 30);
+    assert!(loaded[PLAYER].hunger.is_some());
+    let names: Vec<&str> = loaded[PLAYER].inventory.iter().map(|item| item.name.as_str()).collect();
+    assert_eq!(names, vec!["sword", "healing potion"]);
+    let sword = loaded[PLAYER].inventory[0].equipment.unwrap();
+    assert!(sword.equipped && !sword.two_handed);
+    assert_eq!(sword.damage_bonus, 1);
+    assert!(loaded.iter().all(|e| e.inventory.is_empty() || e.name == "Player"));
+    assert_eq!(loaded_game.dungeon_level, 1);
+    assert!(loaded_game.levels.is_empty());
+    assert_eq!(loaded_game.turns, 0);
+    assert!(!loaded_game.casual);
+    // the old flags became terrain, and nothing was hidden behind made-up appearances back then
+    assert_eq!(loaded_game.map[0][0].terrain, Terrain::Wall);
+    assert!(crate::find_terrain(&loaded_game.map, Terrain::DownStairs).is_some());
+    assert!(loaded_game.is_identified("healing potion"));
+    // and its 20x20 map sits in the corner of today's bigger one
+    assert_eq!(loaded_game.map.len(), MAP_WIDTH as usize);
+    assert_eq!(loaded_game.map[2][5].terrain, Terrain::Floor);
+    assert_eq!(loaded_game.map[MAP_WIDTH as usize - 1][MAP_HEIGHT as usize - 1].terrain, Terrain::Wall);
+
+    // the current format round trips
+    let (loaded, _) = from_json(&to_json(&entities, &game).unwrap()).unwrap();
+    assert_eq!(loaded.len(), 1);
+
+    let too_new = json!({ "format_version": FORMAT_VERSION + 1, "game_version": "9.9.9", "data": {} });
+    assert!(matches!(from_json(&too_new.to_string()), Err(SaveError::TooNew { .. })));
+    assert!(matches!(from_json("not json"), Err(SaveError::Corrupt(_))));
+
+    // hand edits are caught, including pretending to be from before checksums
+    let edited = to_json(&entities, &game).unwrap().replace("\"hp\":", "\"hp\":1");
+    assert!(matches!(from_json(&edited), Err(SaveError::BadChecksum)));
+    let mut unchecked: Value = serde_json::from_str(&edited).unwrap();
+    unchecked["format_version"] = json!(1);
+    unchecked.as_object_mut().unwrap().remove("checksum");
+    assert!(matches!(from_json(&unchecked.to_string()), Err(SaveError::BadChecksum)));
+    // and the old format only comes in through import_legacy
+    assert!(matches!(from_json(baseline), Err(SaveError::Corrupt(_))));
+
+    // the slot list reads the summary without the game
+    let header: Header = serde_json::from_str(&to_json(&entities, &game).unwrap()).unwrap();
+    assert_eq!(header.summary, Some(Summary { name: "Player".to_string(), depth: 1, turns: 0 }));
+    // which is also how old .json slots are told apart from the scores and config
+    assert!(serde_json::from_str::<Header>(r#"{ "format_version": 1, "scores": [] }"#).is_err());
+    assert!(serde_json::from_str::<Header>(r#"{ "tileset": "cp437" }"#).is_err());
+
+    let nobody: Vec<Entity> = Vec::new();
+    assert!(matches!(from_json(&to_json(&nobody, &game).unwrap()), Err(SaveError::Invalid(_))));
+    assert_eq!(slot_name("Sir Bob/1"), "Sir_Bob_1");
+}
@@ -0,0 +1,176 @@
+//! The high-score table, kept next to the saves in `saves/scores.json`. Only permadeath games
+//! are recorded; a casual game can die any number of times from the same save.
+//!
+//! The file is plain JSON so other tools can read it. It looks like this, best score first:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "scores": [
+//!     {
+//!       "name": "Bob",
+//!       "score": 1830,
+//!       "depth": 3,
+//!       "turns": 420,
+//!       "kills": 5,
+//!       "xp": 50,
+//!       "cause": "a kobold",
+//!       "victory": false,
+//!       "seed": 9876543210
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `depth` is the deepest dungeon level reached, `xp` is the total max HP of everything killed,
+//! `cause` is what dealt the killing blow (empty for a victory) and `seed` is the run's seed,
+//! which regenerates the same dungeon (see rng.rs). `score` comes from [`score`].
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::save::{FileError, SAVE_DIR};
+use crate::{Entity, Game, PLAYER};
+
+const FORMAT_VERSION: u32 = 1;
+// how many entries the file keeps, and how many get shown
+const MAX_SCORES: usize = 100;
+const SHOWN_SCORES: usize = 10;
+
+pub const VICTORY_BONUS: u32 = 5000;
+
+#[derive(Serialize, Deserialize)]
+struct ScoreFile {
+    format_version: u32,
+    scores: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub depth: u32,
+    pub turns: u32,
+    pub kills: u32,
+    pub xp: u32,
+    pub cause: String,
+    pub victory: bool,
+    pub seed: u64,
+}
+
+/// 500 per dungeon level reached, 10 per point of XP, 25 per kill and the victory bonus,
+/// less a point for every 10 turns taken so quicker runs come out ahead
+pub fn score(depth: u32, kills: u32, xp: u32, turns: u32, victory: bool) -> u32 {
+    let bonus = if victory { VICTORY_BONUS } else { 0 };
+    (depth * 500 + xp * 10 + kills * 25 + bonus).saturating_sub(turns / 10)
+}
+
+impl Entry {
+    /// the entry for the game that just ended
+    pub fn new(entities: &[Entity], game: &Game, victory: bool) -> Self {
+        let player = &entities[PLAYER];
+        let depth = game.max_depth();
+        let kills = game.kills.values().sum();
+        Entry {
+            name: player.name.clone(),
+            score: score(depth, kills, game.xp, game.turns, victory),
+            depth,
+            turns: game.turns,
+            kills,
+            xp: game.xp,
+            cause: if victory { String::new() } else { player.killed_by.clone().unwrap_or_default() },
+            victory,
+            seed: game.seed,
+        }
+    }
+}
+
+fn scores_path() -> PathBuf {
+    PathBuf::from(SAVE_DIR).join("scores.json")
+}
+
+/// all the recorded scores, best first; no file yet just means no scores
+pub fn load() -> Result<Vec<Entry>, FileError> {
+    let json = match fs::read_to_string(scores_path()) {
+        Ok(json) => json,
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let file: ScoreFile = serde_json::from_str(&json)?;
+    if file.format_version > FORMAT_VERSION {
+        return Err(FileError::TooNew { format_version: file.format_version, supported: FORMAT_VERSION });
+    }
+    Ok(file.scores)
+}
+
+/// put a new entry in its place in the table; returns the table and where the entry ended up
+pub fn record(entry: Entry) -> Result<(Vec<Entry>, Option<usize>), FileError> {
+    let mut scores = load()?;
+    let rank = insert(&mut scores, entry);
+    fs::create_dir_all(SAVE_DIR)?;
+    let path = scores_path();
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, serde_json::to_string_pretty(&ScoreFile { format_version: FORMAT_VERSION, scores: scores.clone() })?)?;
+    fs::rename(&temp, &path)?;
+    Ok((scores, rank))
+}
+
+/// ties go to whoever got there first; None if it didn't make the cut
+fn insert(scores: &mut Vec<Entry>, entry: Entry) -> Option<usize> {
+    let rank = scores.iter().position(|other| other.score < entry.score).unwrap_or(scores.len());
+    scores.insert(rank, entry);
+    scores.truncate(MAX_SCORES);
+    if rank < scores.len() {
+        Some(rank)
+    } else {
+        None
+    }
+}
+
+/// the top of the table, marking the given entry
+pub fn table(scores: &[Entry], highlight: Option<usize>) -> String {
+    if scores.is_empty() {
+        return "No high scores yet.".to_string();
+    }
+    let mut text = String::from("High scores\n");
+    for (rank, entry) in scores.iter().enumerate().take(SHOWN_SCORES) {
+        let fate = if entry.victory { "won!".to_string() } else { format!("killed by {}", entry.cause) };
+        let marker = if Some(rank) == highlight { '>' } else { ' ' };
+        text.push_str(&format!(
+            "{}{:2}. {:6}  {}, {} on dungeon level {} after {} turns\n",
+            marker,
+            rank + 1,
+            entry.score,
+            entry.name,
+            fate,
+            entry.depth,
+            entry.turns
+        ));
+    }
+    text
+}
+
+#[test]
+fn score_tests() {
+    assert_eq!(score(2, 3, 30, 100, false), 1000 + 300 + 75 - 10);
+    assert_eq!(score(1, 0, 0, 0, true), 500 + VICTORY_BONUS);
+    assert_eq!(score(0, 0, 0, 1000, false), 0);
+
+    let entry = |name: &str, score: u32| Entry {
+        name: name.to_string(),
+        score,
+        depth: 1,
+        turns: 10,
+        kills: 0,
+        xp: 0,
+        cause: "a kobold".to_string(),
+        victory: false,
+        seed: 1,
+    };
+    let mut scores = vec![entry("a", 300), entry("b", 100)];
+    assert_eq!(insert(&mut scores, entry("c", 200)), Some(1));
+    assert_eq!(insert(&mut scores, entry("d", 100)), Some(3));
+    let names: Vec<&str> = scores.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "c", "b", "d"]);
+    assert!(table(&scores, Some(1)).contains("> 2.    200  c, killed by a kobold"));
+}
@@ -0,0 +1,177 @@
+//! Reading the player's input. In line mode (the default) every command is a line of text;
+//! in raw mode the terminal hands us each keypress straight away and the screen is redrawn
+//! in place. Raw mode goes through `stty`, so it needs a real (Unix) terminal.
+
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static RAW: AtomicBool = AtomicBool::new(false);
+// set once the player presses Ctrl-C in raw mode; from then on it's as if input had run out
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_raw() -> bool {
+    RAW.load(Ordering::Relaxed)
+}
+
+/// While this is alive the terminal is in raw mode; dropping it puts the old settings back,
+/// even if we panic.
+pub struct RawMode {
+    saved: String, //what `stty -g` said before we changed anything
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        RAW.store(false, Ordering::Relaxed);
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// switch to raw mode; None if there's no terminal to do it on
+pub fn enter_raw() -> Option<RawMode> {
+    if !stdin().is_terminal() {
+        return None;
+    }
+    let saved = stty(&["-g"])?;
+    // no line editing, no echo and no signals from Ctrl-C (read_key deals with it, so the
+    // terminal gets put back), but keep output processing so \n still starts a new line
+    stty(&["-icanon", "-echo", "-isig", "min", "1", "time", "0"])?;
+    RAW.store(true, Ordering::Relaxed);
+    Some(RawMode { saved })
+}
+
+/// put the cursor in the top left corner and wipe everything from there down
+pub fn clear_screen() {
+    move_to(0, 0);
+    print!("{}", CLEAR_BELOW);
+}
+
+/// put the cursor at a screen position, counting from 0
+pub fn move_to(x: i32, y: i32) {
+    print!("{}", cursor_to(x, y));
+}
+
+/// the escape sequence move_to prints, for building up output to send in one go
+pub fn cursor_to(x: i32, y: i32) -> String {
+    format!("\x1b[{};{}H", y + 1, x + 1)
+}
+
+/// wipes the rest of the line the cursor is on
+pub const CLEAR_LINE: &str = "\x1b[K";
+/// wipes everything from the cursor down
+pub const CLEAR_BELOW: &str = "\x1b[J";
+
+fn read_byte() -> Option<u8> {
+    let mut byte = [0];
+    match stdin().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+/// the next byte if there is one within a tenth of a second; the rest of an escape sequence
+/// arrives straight after the ESC, while a lone Esc keypress has nothing after it
+fn read_byte_soon() -> Option<u8> {
+    let _ = stty(&["min", "0", "time", "1"]);
+    let byte = read_byte();
+    let _ = stty(&["min", "1", "time", "0"]);
+    byte
+}
+
+/// One keypress; the arrow keys come back as the direction letters (n/s/e/w). None at end of
+/// input, or once Ctrl-C has been pressed.
+pub fn read_key() -> Option<char> {
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let first = read_byte()?;
+    if first == 0x03 {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+        return None;
+    }
+    if first == 0x1b {
+        // arrow keys send ESC [ A..D; anything else is taken as Esc on its own
+        if read_byte_soon() == Some(b'[') {
+            return match read_byte_soon() {
+                Some(b'A') => Some('n'),
+                Some(b'B') => Some('s'),
+                Some(b'C') => Some('e'),
+                Some(b'D') => Some('w'),
+                _ => Some('\x1b'),
+            };
+        }
+        return Some('\x1b');
+    }
+    // the rest of a UTF-8 character
+    let extra = match first {
+        0xc0..=0xdf => 1,
+        0xe0..=0xef => 2,
+        0xf0..=0xf7 => 3,
+        _ => 0,
+    };
+    let mut bytes = vec![first];
+    for _ in 0..extra {
+        bytes.push(read_byte()?);
+    }
+    String::from_utf8_lossy(&bytes).chars().next()
+}
+
+/// Ask for a command: a single key in raw mode, a whole line otherwise. None at end of input.
+pub fn read_command(prompt: &str) -> Option<String> {
+    print!("{}", prompt);
+    let _ = stdout().flush();
+    if is_raw() {
+        let key = read_key()?;
+        println!();
+        return Some(key.to_string());
+    }
+    let mut s = String::new();
+    if stdin().read_line(&mut s).expect("Did not enter a correct command") == 0 {
+        return None;
+    }
+    let s = s.trim_end_matches(&['\n', '\r'][..]).to_string();
+    println!("You typed: {}", s);
+    Some(s)
+}
+
+/// in raw mode the next redraw would wipe whatever we just printed, so wait for the player first
+pub fn pause() {
+    if is_raw() {
+        let _ = read_command("(press any key)");
+    }
+}
+
+/// Ask for some text (a name, a number), up to Enter. Empty at end of input.
+pub fn read_text(prompt: &str) -> String {
+    print!("{}", prompt);
+    let _ = stdout().flush();
+    let mut s = String::new();
+    if is_raw() {
+        // we turned the terminal's echo and editing off, so do them ourselves
+        while let Some(c) = read_key() {
+            match c {
+                '\n' | '\r' => break,
+                '\x7f' | '\x08' if s.pop().is_some() => print!("\x08 \x08"),
+                c if !c.is_control() => {
+                    s.push(c);
+                    print!("{}", c);
+                }
+                _ => {}
+            }
+            let _ = stdout().flush();
+        }
+        println!();
+        return s;
+    }
+    stdin().read_line(&mut s).expect("Did not enter a correct string");
+    s.trim_end_matches(&['\n', '\r'][..]).to_string()
+}
@@ -0,0 +1,153 @@
+//! Tilesets: which character stands for each kind of terrain and thing on screen. Entities keep
+//! a plain ASCII `char` and tiles only know their terrain; what actually gets drawn for them
+//! (`Tile::glyph`, `Tileset::entity`) comes from the tileset picked at startup. That's plain
+//! ASCII unless asked otherwise, the original game's look, which works on any terminal.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{Door, Entity, Item, Terrain};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tileset {
+    /// plain ASCII, for terminals without Unicode fonts
+    #[default]
+    Ascii,
+    /// ASCII with line-drawn walls, ≈ for deep water and a solid health bar
+    Unicode,
+    /// in the style of the old IBM PC code page 437 games: double-line walls, ☺ for the player
+    Cp437,
+}
+
+// the tileset in use, as its position in ALL
+static TILESET: AtomicU8 = AtomicU8::new(0);
+
+impl Tileset {
+    pub const ALL: [Tileset; 3] = [Tileset::Ascii, Tileset::Unicode, Tileset::Cp437];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tileset::Ascii => "ascii",
+            Tileset::Unicode => "unicode",
+            Tileset::Cp437 => "cp437",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Tileset> {
+        Tileset::ALL.iter().copied().find(|set| set.name() == name.trim().to_lowercase())
+    }
+
+    /// anything but walls, which depend on their neighbours (see wall)
+    pub fn terrain(self, terrain: Terrain) -> char {
+        use Tileset::*;
+        match (self, terrain) {
+            (_, Terrain::Wall) => self.wall(false, false, false, false),
+            (Cp437, Terrain::Floor) => '\u{b7}',
+            (_, Terrain::Floor) => '.',
+            (_, Terrain::Water) => '~',
+            // no colour-free way to tell deep water apart in plain ASCII that isn't already taken
+            (Ascii, Terrain::DeepWater) => '~',
+            (_, Terrain::DeepWater) => '\u{2248}',
+            (_, Terrain::Lava) => '}',
+            (Cp437, Terrain::Rubble) => '\u{2591}',
+            (_, Terrain::Rubble) => ';',
+            (_, Terrain::Grass) => ',',
+            (Cp437, Terrain::TallGrass) => '\u{3c4}',
+            (_, Terrain::TallGrass) => '"',
+            (_, Terrain::Chasm) => ':',
+            (_, Terrain::Door(Door::Open)) => '\'',
+            (_, Terrain::Door(_)) => '+',
+            (_, Terrain::UpStairs) => '<',
+            (_, Terrain::DownStairs) => '>',
+        }
+    }
+
+    /// a wall, joined up with whichever neighbours are walls too
+    pub fn wall(self, north: bool, south: bool, east: bool, west: bool) -> char {
+        // indexed by north, south, east, west as bits 3 to 0
+        const SINGLE: [char; 16] = ['■', '─', '─', '─', '│', '┐', '┌', '┬', '│', '┘', '└', '┴', '│', '┤', '├', '┼'];
+        const DOUBLE: [char; 16] = ['■', '═', '═', '═', '║', '╗', '╔', '╦', '║', '╝', '╚', '╩', '║', '╣', '╠', '╬'];
+        let index = (north as usize) << 3 | (south as usize) << 2 | (east as usize) << 1 | west as usize;
+        match self {
+            Tileset::Ascii => '#',
+            Tileset::Unicode => SINGLE[index],
+            Tileset::Cp437 => DOUBLE[index],
+        }
+    }
+
+    /// a found trap
+    pub fn trap(self) -> char {
+        match self {
+            Tileset::Cp437 => '\u{2666}',
+            _ => '^',
+        }
+    }
+
+    /// the filled part of a bar like the HP one
+    pub fn bar(self) -> char {
+        match self {
+            Tileset::Ascii => '#',
+            _ => '\u{2588}',
+        }
+    }
+
+    /// what an entity looks like; monsters keep their letters everywhere
+    pub fn entity(self, entity: &Entity) -> char {
+        if self != Tileset::Cp437 {
+            return entity.char;
+        }
+        // food and corpses share '%' in ASCII, but not here
+        if let Some(Item::Food(_)) = entity.item {
+            return '\u{2663}';
+        }
+        match entity.char {
+            '@' => '\u{263a}',
+            '!' => '\u{a1}',
+            '?' => '\u{266a}',
+            '/' => '\u{2191}',
+            '[' => '\u{25d8}',
+            '=' => '\u{25cb}',
+            '-' => '\u{2310}',
+            c => c,
+        }
+    }
+}
+
+pub fn set_tileset(tileset: Tileset) {
+    let index = Tileset::ALL.iter().position(|&set| set == tileset).unwrap_or(0);
+    TILESET.store(index as u8, Ordering::Relaxed);
+}
+
+pub fn tileset() -> Tileset {
+    Tileset::ALL[TILESET.load(Ordering::Relaxed) as usize]
+}
+
+#[test]
+fn tileset_tests() {
+    use crate::{make_ration, Entity};
+
+    assert_eq!(Tileset::from_name("CP437"), Some(Tileset::Cp437));
+    assert_eq!(Tileset::from_name("ebcdic"), None);
+    // without a config file you get the original look
+    assert_eq!(Tileset::default(), Tileset::Ascii);
+    assert_eq!(Tileset::default(), tileset());
+
+    // nothing outside ASCII in the ASCII set
+    let terrains = [Terrain::Floor, Terrain::Wall, Terrain::DeepWater, Terrain::TallGrass, Terrain::Door(Door::Open), Terrain::DownStairs];
+    assert!(terrains.iter().all(|&t| Tileset::Ascii.terrain(t).is_ascii()));
+    assert!(Tileset::Ascii.bar().is_ascii() && Tileset::Ascii.trap().is_ascii());
+    assert_eq!(Tileset::Unicode.terrain(Terrain::DeepWater), '≈');
+
+    // a corner with walls to the south and east, and a T joining three
+    assert_eq!(Tileset::Unicode.wall(false, true, true, false), '┌');
+    assert_eq!(Tileset::Cp437.wall(true, false, true, true), '╩');
+    assert_eq!(Tileset::Ascii.wall(true, true, true, true), '#');
+
+    let player = Entity::new(0, 0, '@', "Player");
+    let mut corpse = Entity::new(0, 0, '%', "remains of kobold");
+    corpse.blocks = false;
+    assert_eq!(Tileset::Unicode.entity(&player), '@');
+    assert_eq!(Tileset::Cp437.entity(&player), '☺');
+    assert_eq!(Tileset::Cp437.entity(&corpse), '%');
+    assert_eq!(Tileset::Cp437.entity(&make_ration(0, 0)), '♣');
+}